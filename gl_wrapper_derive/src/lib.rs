@@ -0,0 +1,100 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+/// Derives `gl_wrapper::shader::UniformContainer` for a struct with named fields.
+///
+/// Uniform locations are looked up lazily and cached per `Program`, so the same
+/// container can be bound to every program that shares the uniform names.
+/// Uniforms missing from a program are skipped.
+///
+/// Fields can hold anything implementing `UniformValue`, including `Texture` and
+/// `TextureBinding` for sampler uniforms, which get texture units assigned when drawing.
+///
+/// Fields accept `#[uniform(name = "u_ModelView")]` to use a different GLSL name
+/// and `#[uniform(skip)]` for fields that aren't uniforms.
+#[proc_macro_derive(UniformContainer, attributes(uniform))]
+pub fn derive_uniform_container(input: TokenStream) -> TokenStream{
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2>{
+    let name = &input.ident;
+
+    let fields = match &input.data{
+        Data::Struct(data) => match &data.fields{
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "UniformContainer can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "UniformContainer can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut generics = input.generics.clone();
+    let mut binds = Vec::new();
+
+    for field in fields{
+        let ident = field.ident.as_ref().unwrap();
+        let mut glsl_name = ident.to_string();
+        let mut skip = false;
+
+        for attr in field.attrs.iter().filter(|x| x.path().is_ident("uniform")){
+            attr.parse_nested_meta(|meta|{
+                if meta.path.is_ident("skip"){
+                    skip = true;
+                    Ok(())
+                }
+                else if meta.path.is_ident("name"){
+                    glsl_name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                }
+                else{
+                    Err(meta.error("expected `name = \"...\"` or `skip`"))
+                }
+            })?;
+        }
+
+        if skip{
+            continue;
+        }
+
+        let ty = &field.ty;
+        generics
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote!(#ty: ::gl_wrapper::shader::UniformValue));
+
+        binds.push(quote! {
+            program.with_cached_uniform(#glsl_name, |uniform|{
+                if let ::std::result::Result::Err(e) = ::gl_wrapper::shader::UniformValue::write(&self.#ident, uniform, units){
+                    if cfg!(debug_assertions){
+                        panic!("Failed to set uniform {}: {}", #glsl_name, e);
+                    }
+                }
+            });
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::gl_wrapper::shader::UniformContainer for #name #ty_generics #where_clause{
+            fn bind(&self, program: &::gl_wrapper::shader::Program, units: &mut ::gl_wrapper::texture::TextureUnits){
+                #(#binds)*
+            }
+        }
+    })
+}
@@ -1,6 +1,6 @@
-use std::{rc::Rc, mem::size_of};
+use std::{rc::Rc, mem::size_of_val, slice, time::Duration};
 
-use crate::internal::{RawId, RawIdManager};
+use crate::{internal::{RawId, RawIdManager}, sync::{Fence, WaitResult}};
 
 #[derive(Clone)]
 pub struct Buffer{
@@ -18,7 +18,7 @@ impl Buffer{
             self.bind();
             gl::BufferData(
                 self.target,
-                (size_of::<T>() * data.len()) as isize,
+                size_of_val(data) as isize,
                 data.as_ptr().cast(),
                 usage
             );
@@ -26,10 +26,28 @@ impl Buffer{
         }
     }
 
+    /// Allocates `size` bytes of uninitialized storage.
+    pub fn allocate(&self, size: usize, usage: u32){
+        unsafe{
+            gl::NamedBufferData(self.id(), size as isize, std::ptr::null(), usage);
+        }
+    }
+
+    /// Copies the buffer contents starting at byte `offset` into `out`.
+    pub fn read_data<T: Copy>(&self, offset: usize, out: &mut [T]){
+        unsafe{
+            gl::GetNamedBufferSubData(self.id(), offset as isize, size_of_val(out) as isize, out.as_mut_ptr().cast());
+        }
+    }
+
     pub fn target(&self) -> u32{
         self.target
     }
 
+    pub(crate) fn id(&self) -> u32{
+        self.id.id()
+    }
+
     pub(crate) fn bind(&self){
         unsafe{
             gl::BindBuffer(self.target,self.id.id());
@@ -57,4 +75,118 @@ impl RawIdManager for Buffer{
             gl::DeleteBuffers(1, &id);
         }
     }
+}
+
+/// A buffer mapped once for writing and split into regions that are filled round robin,
+/// for streaming per-frame data without reallocating or mapping every frame.
+///
+/// Moving to the next region fences the previous one, and a region is only handed out
+/// again once the GPU passed its fence, so data isn't overwritten while it's read.
+pub struct RingBuffer{
+    buffer: Buffer,
+    ptr: *mut u8,
+    region_size: usize,
+    fences: Vec<Option<Fence>>,
+    current: Option<usize>,
+}
+
+impl RingBuffer{
+    /// Allocates `regions` regions of at least `region_size` bytes, rounded up to the
+    /// offset alignment of uniform and shader storage buffers.
+    ///
+    /// Panics if `regions` is 0 or the buffer can't be mapped.
+    pub fn new(target: u32, region_size: usize, regions: usize) -> Self{
+        assert!(regions > 0, "A ring buffer needs at least one region");
+        let buffer = Buffer::new(target);
+        let alignment = match target{
+            gl::UNIFORM_BUFFER => Some(gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT),
+            gl::SHADER_STORAGE_BUFFER => Some(gl::SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT),
+            _ => None
+        };
+        unsafe{
+            let mut align = 1;
+            if let Some(pname) = alignment{
+                gl::GetIntegerv(pname, &mut align);
+            }
+            let region_size = region_size.next_multiple_of(align.max(1) as usize);
+            let size = region_size.checked_mul(regions)
+                .and_then(|x| isize::try_from(x).ok())
+                .expect("Ring buffer size overflows");
+            let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+            gl::NamedBufferStorage(buffer.id(), size, std::ptr::null(), flags);
+            let ptr: *mut u8 = gl::MapNamedBufferRange(buffer.id(), 0, size, flags).cast();
+            if ptr.is_null(){
+                panic!("Failed to map a ring buffer of {} bytes", size);
+            }
+
+            Self{
+                buffer,
+                ptr,
+                region_size,
+                fences: (0..regions).map(|_| None).collect(),
+                current: None
+            }
+        }
+    }
+
+    /// Fences the region handed out before and returns the byte offset and memory of the
+    /// next one, waiting until the GPU is done reading it.
+    ///
+    /// Commands reading the previous region have to be issued before calling this.
+    /// Panics if waiting for the region fails, which happens when the context is lost.
+    pub fn next_region(&mut self) -> (usize, &mut [u8]){
+        let next = match self.current{
+            Some(current) => {
+                self.fences[current] = Some(Fence::new());
+                (current + 1) % self.fences.len()
+            },
+            None => 0
+        };
+        if let Some(fence) = self.fences[next].take(){
+            loop{
+                match fence.client_wait(Duration::from_secs(1)){
+                    WaitResult::TimeoutExpired => continue,
+                    WaitResult::Failed => panic!("Waiting for a ring buffer region failed"),
+                    _ => break
+                }
+            }
+        }
+        self.current = Some(next);
+
+        let offset = next * self.region_size;
+        unsafe{
+            (offset, slice::from_raw_parts_mut(self.ptr.add(offset), self.region_size))
+        }
+    }
+
+    /// Copies `data` to the start of the next region and returns its byte offset.
+    ///
+    /// Panics if `data` is larger than a region.
+    pub fn write<T: Copy>(&mut self, data: &[T]) -> usize{
+        let size = size_of_val(data);
+        let (offset, region) = self.next_region();
+        if size > region.len(){
+            panic!("{} bytes don't fit a ring buffer region of {}", size, region.len());
+        }
+        unsafe{
+            std::ptr::copy_nonoverlapping(data.as_ptr().cast(), region.as_mut_ptr(), size);
+        }
+        offset
+    }
+
+    pub fn region_size(&self) -> usize{
+        self.region_size
+    }
+
+    pub fn buffer(&self) -> &Buffer{
+        &self.buffer
+    }
+}
+
+impl Drop for RingBuffer{
+    fn drop(&mut self) {
+        unsafe{
+            gl::UnmapNamedBuffer(self.buffer.id());
+        }
+    }
 }
\ No newline at end of file
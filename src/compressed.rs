@@ -0,0 +1,572 @@
+//! Loading block compressed textures (BC1–BC7, ASTC) from KTX2 and DDS containers.
+
+use std::{error::Error, fmt::{self, Display, Formatter}, fs, io, path::Path};
+
+use crate::{ext, texture::Texture};
+
+// S3TC and ASTC are extensions, the `gl` crate only has the core RGTC and BPTC formats.
+const COMPRESSED_RGB_S3TC_DXT1: u32 = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT1: u32 = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3: u32 = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5: u32 = 0x83F3;
+const COMPRESSED_SRGB_S3TC_DXT1: u32 = 0x8C4C;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: u32 = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: u32 = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: u32 = 0x8C4F;
+const COMPRESSED_RGBA_ASTC_4X4: u32 = 0x93B0;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_4X4: u32 = 0x93D0;
+
+/// ASTC block sizes in the order of their GL and Vulkan format enums.
+const ASTC_BLOCKS: [(u32, u32); 14] = [
+    (4, 4), (5, 4), (5, 5), (6, 5), (6, 6), (8, 5), (8, 6),
+    (8, 8), (10, 5), (10, 6), (10, 8), (10, 10), (12, 10), (12, 12),
+];
+
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+
+#[derive(Debug)]
+pub enum CompressedTextureError{
+    Io(io::Error),
+    /// The file ends before the data its header describes.
+    Truncated,
+    Malformed(&'static str),
+    /// The container stores a format that isn't one of the block compressed formats.
+    UnsupportedFormat(String),
+    /// A container feature like supercompression or 3D textures.
+    Unsupported(&'static str),
+    /// The driver lacks the extension for the format.
+    MissingExtension(&'static str),
+}
+
+impl Display for CompressedTextureError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self{
+            CompressedTextureError::Io(e) => Display::fmt(e, f),
+            CompressedTextureError::Truncated => write!(f, "File is truncated"),
+            CompressedTextureError::Malformed(x) => write!(f, "Malformed file: {}", x),
+            CompressedTextureError::UnsupportedFormat(x) => write!(f, "Unsupported format {}", x),
+            CompressedTextureError::Unsupported(x) => write!(f, "Unsupported: {}", x),
+            CompressedTextureError::MissingExtension(x) => write!(f, "The format requires {}", x),
+        }
+    }
+}
+
+impl Error for CompressedTextureError{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self{
+            CompressedTextureError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for CompressedTextureError{
+    fn from(x: io::Error) -> Self {
+        Self::Io(x)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompressedFormat{
+    pub internal_format: u32,
+    pub block_width: u32,
+    pub block_height: u32,
+    pub block_bytes: u32,
+    extension: Option<&'static str>,
+}
+
+impl CompressedFormat{
+    const fn new(internal_format: u32, block_bytes: u32, extension: Option<&'static str>) -> Self{
+        Self{
+            internal_format,
+            block_width: 4,
+            block_height: 4,
+            block_bytes,
+            extension
+        }
+    }
+
+    fn s3tc(internal_format: u32, block_bytes: u32, srgb: bool) -> Self{
+        Self::new(internal_format, block_bytes, Some(if srgb { "GL_EXT_texture_sRGB" } else { "GL_EXT_texture_compression_s3tc" }))
+    }
+
+    fn astc(index: u32, srgb: bool) -> Self{
+        let (block_width, block_height) = ASTC_BLOCKS[index as usize];
+        let base = if srgb { COMPRESSED_SRGB8_ALPHA8_ASTC_4X4 } else { COMPRESSED_RGBA_ASTC_4X4 };
+        Self{
+            internal_format: base + index,
+            block_width,
+            block_height,
+            block_bytes: 16,
+            extension: Some("GL_KHR_texture_compression_astc_ldr")
+        }
+    }
+
+    /// Maps a Vulkan `VkFormat`, as stored in KTX2.
+    pub fn from_vk_format(format: u32) -> Option<Self>{
+        Some(match format{
+            131 => Self::s3tc(COMPRESSED_RGB_S3TC_DXT1, 8, false),
+            132 => Self::s3tc(COMPRESSED_SRGB_S3TC_DXT1, 8, true),
+            133 => Self::s3tc(COMPRESSED_RGBA_S3TC_DXT1, 8, false),
+            134 => Self::s3tc(COMPRESSED_SRGB_ALPHA_S3TC_DXT1, 8, true),
+            135 => Self::s3tc(COMPRESSED_RGBA_S3TC_DXT3, 16, false),
+            136 => Self::s3tc(COMPRESSED_SRGB_ALPHA_S3TC_DXT3, 16, true),
+            137 => Self::s3tc(COMPRESSED_RGBA_S3TC_DXT5, 16, false),
+            138 => Self::s3tc(COMPRESSED_SRGB_ALPHA_S3TC_DXT5, 16, true),
+            139 => Self::new(gl::COMPRESSED_RED_RGTC1, 8, None),
+            140 => Self::new(gl::COMPRESSED_SIGNED_RED_RGTC1, 8, None),
+            141 => Self::new(gl::COMPRESSED_RG_RGTC2, 16, None),
+            142 => Self::new(gl::COMPRESSED_SIGNED_RG_RGTC2, 16, None),
+            143 => Self::new(gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT, 16, None),
+            144 => Self::new(gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT, 16, None),
+            145 => Self::new(gl::COMPRESSED_RGBA_BPTC_UNORM, 16, None),
+            146 => Self::new(gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM, 16, None),
+            157..=184 => Self::astc((format - 157) / 2, format.is_multiple_of(2)),
+            _ => return None
+        })
+    }
+
+    /// Maps a `DXGI_FORMAT`, as stored in the DX10 header of DDS files.
+    pub fn from_dxgi_format(format: u32) -> Option<Self>{
+        Some(match format{
+            70 | 71 => Self::s3tc(COMPRESSED_RGBA_S3TC_DXT1, 8, false),
+            72 => Self::s3tc(COMPRESSED_SRGB_ALPHA_S3TC_DXT1, 8, true),
+            73 | 74 => Self::s3tc(COMPRESSED_RGBA_S3TC_DXT3, 16, false),
+            75 => Self::s3tc(COMPRESSED_SRGB_ALPHA_S3TC_DXT3, 16, true),
+            76 | 77 => Self::s3tc(COMPRESSED_RGBA_S3TC_DXT5, 16, false),
+            78 => Self::s3tc(COMPRESSED_SRGB_ALPHA_S3TC_DXT5, 16, true),
+            79 | 80 => Self::new(gl::COMPRESSED_RED_RGTC1, 8, None),
+            81 => Self::new(gl::COMPRESSED_SIGNED_RED_RGTC1, 8, None),
+            82 | 83 => Self::new(gl::COMPRESSED_RG_RGTC2, 16, None),
+            84 => Self::new(gl::COMPRESSED_SIGNED_RG_RGTC2, 16, None),
+            94 | 95 => Self::new(gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT, 16, None),
+            96 => Self::new(gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT, 16, None),
+            97 | 98 => Self::new(gl::COMPRESSED_RGBA_BPTC_UNORM, 16, None),
+            99 => Self::new(gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM, 16, None),
+            _ => return None
+        })
+    }
+
+    /// Maps the FourCC codes of legacy DDS files.
+    pub fn from_four_cc(four_cc: &[u8; 4]) -> Option<Self>{
+        Some(match four_cc{
+            b"DXT1" => Self::s3tc(COMPRESSED_RGBA_S3TC_DXT1, 8, false),
+            b"DXT2" | b"DXT3" => Self::s3tc(COMPRESSED_RGBA_S3TC_DXT3, 16, false),
+            b"DXT4" | b"DXT5" => Self::s3tc(COMPRESSED_RGBA_S3TC_DXT5, 16, false),
+            b"ATI1" | b"BC4U" => Self::new(gl::COMPRESSED_RED_RGTC1, 8, None),
+            b"BC4S" => Self::new(gl::COMPRESSED_SIGNED_RED_RGTC1, 8, None),
+            b"ATI2" | b"BC5U" => Self::new(gl::COMPRESSED_RG_RGTC2, 16, None),
+            b"BC5S" => Self::new(gl::COMPRESSED_SIGNED_RG_RGTC2, 16, None),
+            _ => return None
+        })
+    }
+
+    /// Size of one image of `width` x `height` pixels, `None` if it doesn't fit in a `usize`.
+    pub fn image_size(&self, width: u32, height: u32) -> Option<usize>{
+        (width.div_ceil(self.block_width) as usize)
+            .checked_mul(height.div_ceil(self.block_height) as usize)?
+            .checked_mul(self.block_bytes as usize)
+    }
+
+    fn level_size(&self, width: u32, height: u32, level: u32) -> Result<usize, CompressedTextureError>{
+        self.image_size(level_extent(width, level), level_extent(height, level))
+            .ok_or(CompressedTextureError::Malformed("image size overflows"))
+    }
+}
+
+/// Size of a mip level along one axis.
+fn level_extent(size: u32, level: u32) -> u32{
+    size.checked_shr(level).unwrap_or(0).max(1)
+}
+
+/// Checks the size and level count before they are used for shifts and allocations.
+fn check_size(width: u32, height: u32, level_count: u32) -> Result<(), CompressedTextureError>{
+    if width == 0 || height == 0{
+        return Err(CompressedTextureError::Malformed("width and height can't be 0"));
+    }
+    if width > i32::MAX as u32 || height > i32::MAX as u32{
+        return Err(CompressedTextureError::Malformed("width and height are too large"));
+    }
+    if level_count > 32 - width.max(height).leading_zeros(){
+        return Err(CompressedTextureError::Malformed("more mip levels than the size allows"));
+    }
+    Ok(())
+}
+
+/// Images per level, which GL takes as an `i32` layer count.
+fn image_count(layers: u32, faces: u32) -> Result<u32, CompressedTextureError>{
+    layers.max(1).checked_mul(faces)
+        .filter(|&x| x <= i32::MAX as u32)
+        .ok_or(CompressedTextureError::Malformed("too many layers"))
+}
+
+/// The images of a container, borrowed from the file contents.
+#[derive(Debug)]
+struct CompressedImages<'a>{
+    format: CompressedFormat,
+    width: u32,
+    height: u32,
+    /// Array layers, 0 if the texture isn't an array.
+    layers: u32,
+    /// 6 for cube maps, 1 otherwise.
+    faces: u32,
+    /// Per mip level, the images ordered by layer and then face.
+    levels: Vec<Vec<&'a [u8]>>,
+}
+
+impl CompressedImages<'_>{
+    fn upload(&self) -> Result<Texture, CompressedTextureError>{
+        if let Some(extension) = self.format.extension{
+            if !ext::has_extension(extension){
+                return Err(CompressedTextureError::MissingExtension(extension));
+            }
+        }
+
+        let (width, height, levels) = (self.width as i32, self.height as i32, self.levels.len() as i32);
+        let internal_format = self.format.internal_format;
+        let texture = match (self.faces, self.layers){
+            (6, 0) => Texture::new_cube(internal_format, width, levels),
+            (6, layers) => Texture::new_cube_array(internal_format, width, layers as i32, levels),
+            (_, 0) => Texture::new_2d(internal_format, width, height, levels),
+            (_, layers) => Texture::new_2d_array(internal_format, width, height, layers as i32, levels),
+        };
+
+        for (level, images) in self.levels.iter().enumerate(){
+            let level_width = level_extent(self.width, level as u32) as i32;
+            let level_height = level_extent(self.height, level as u32) as i32;
+            for (z, image) in images.iter().enumerate(){
+                texture.set_compressed_sub_image_3d(level as i32, 0, 0, z as i32, level_width, level_height, 1, internal_format, image);
+            }
+        }
+        Ok(texture)
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, CompressedTextureError>{
+    data.get(offset..offset + 4)
+        .map(|x| u32::from_le_bytes(x.try_into().unwrap()))
+        .ok_or(CompressedTextureError::Truncated)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, CompressedTextureError>{
+    data.get(offset..offset + 8)
+        .map(|x| u64::from_le_bytes(x.try_into().unwrap()))
+        .ok_or(CompressedTextureError::Truncated)
+}
+
+fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8], CompressedTextureError>{
+    offset.checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(CompressedTextureError::Truncated)
+}
+
+fn parse_ktx2(data: &[u8]) -> Result<CompressedImages<'_>, CompressedTextureError>{
+    if !data.starts_with(&KTX2_IDENTIFIER){
+        return Err(CompressedTextureError::Malformed("missing KTX2 identifier"));
+    }
+    let vk_format = read_u32(data, 12)?;
+    let width = read_u32(data, 20)?;
+    let height = read_u32(data, 24)?;
+    let depth = read_u32(data, 28)?;
+    let layers = read_u32(data, 32)?;
+    let faces = read_u32(data, 36)?;
+    let level_count = read_u32(data, 40)?;
+    let supercompression = read_u32(data, 44)?;
+
+    if supercompression != 0{
+        return Err(CompressedTextureError::Unsupported("supercompressed KTX2 files"));
+    }
+    if height == 0 || depth != 0{
+        return Err(CompressedTextureError::Unsupported("1D and 3D textures"));
+    }
+    if faces != 1 && faces != 6{
+        return Err(CompressedTextureError::Malformed("face count has to be 1 or 6"));
+    }
+    let format = CompressedFormat::from_vk_format(vk_format)
+        .ok_or_else(|| CompressedTextureError::UnsupportedFormat(format!("VkFormat {}", vk_format)))?;
+
+    // a level count of 0 asks for generated mips, which compressed formats can't do
+    let level_count = level_count.max(1);
+    check_size(width, height, level_count)?;
+    let images = image_count(layers, faces)?;
+    let mut levels = Vec::with_capacity(level_count as usize);
+    for level in 0..level_count{
+        let index = 80 + level as usize * 24;
+        let offset = read_u64(data, index)? as usize;
+        let length = read_u64(data, index + 8)? as usize;
+
+        let size = format.level_size(width, height, level)?;
+        if Some(length) != size.checked_mul(images as usize){
+            return Err(CompressedTextureError::Malformed("level size doesn't match the format"));
+        }
+        let level_data = slice(data, offset, length)?;
+        levels.push(level_data.chunks_exact(size).collect());
+    }
+
+    Ok(CompressedImages{
+        format,
+        width,
+        height,
+        layers,
+        faces,
+        levels
+    })
+}
+
+fn parse_dds(data: &[u8]) -> Result<CompressedImages<'_>, CompressedTextureError>{
+    if !data.starts_with(b"DDS "){
+        return Err(CompressedTextureError::Malformed("missing DDS magic"));
+    }
+    if read_u32(data, 4)? != 124{
+        return Err(CompressedTextureError::Malformed("unexpected header size"));
+    }
+    let flags = read_u32(data, 8)?;
+    let height = read_u32(data, 12)?;
+    let width = read_u32(data, 16)?;
+    let level_count = read_u32(data, 28)?;
+    let pixel_format_flags = read_u32(data, 80)?;
+    let four_cc: [u8; 4] = slice(data, 84, 4)?.try_into().unwrap();
+    let caps2 = read_u32(data, 112)?;
+
+    const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+    const DDPF_FOURCC: u32 = 0x4;
+    const DDSCAPS2_CUBEMAP: u32 = 0x200;
+    const DDSCAPS2_CUBEMAP_ALLFACES: u32 = 0xFC00;
+    const DDSCAPS2_VOLUME: u32 = 0x200000;
+    const RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+    const RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+
+    if pixel_format_flags & DDPF_FOURCC == 0{
+        return Err(CompressedTextureError::UnsupportedFormat("uncompressed DDS".to_string()));
+    }
+    if caps2 & DDSCAPS2_VOLUME != 0{
+        return Err(CompressedTextureError::Unsupported("3D textures"));
+    }
+
+    let mut offset = 128;
+    let (format, mut faces, layers) = if &four_cc == b"DX10"{
+        let dxgi_format = read_u32(data, 128)?;
+        let dimension = read_u32(data, 132)?;
+        let misc = read_u32(data, 136)?;
+        let array_size = read_u32(data, 140)?;
+        offset += 20;
+
+        if dimension != RESOURCE_DIMENSION_TEXTURE2D{
+            return Err(CompressedTextureError::Unsupported("1D and 3D textures"));
+        }
+        let format = CompressedFormat::from_dxgi_format(dxgi_format)
+            .ok_or_else(|| CompressedTextureError::UnsupportedFormat(format!("DXGI_FORMAT {}", dxgi_format)))?;
+        let faces = if misc & RESOURCE_MISC_TEXTURECUBE != 0 { 6 } else { 1 };
+        (format, faces, if array_size > 1 { array_size } else { 0 })
+    }
+    else{
+        let format = CompressedFormat::from_four_cc(&four_cc)
+            .ok_or_else(|| CompressedTextureError::UnsupportedFormat(format!("FourCC {:?}", String::from_utf8_lossy(&four_cc))))?;
+        (format, 1, 0)
+    };
+    if caps2 & DDSCAPS2_CUBEMAP != 0{
+        if caps2 & DDSCAPS2_CUBEMAP_ALLFACES != DDSCAPS2_CUBEMAP_ALLFACES{
+            return Err(CompressedTextureError::Unsupported("cube maps with missing faces"));
+        }
+        faces = 6;
+    }
+
+    // the mip count is only meaningful with its flag, some writers leave garbage in it
+    let level_count = if flags & DDSD_MIPMAPCOUNT != 0 { level_count.max(1) } else { 1 };
+    check_size(width, height, level_count)?;
+
+    // DDS stores each image's full mip chain after another, GL wants each level's images together
+    let mut levels = vec![Vec::new(); level_count as usize];
+    for _ in 0..image_count(layers, faces)?{
+        for (level, images) in levels.iter_mut().enumerate(){
+            let size = format.level_size(width, height, level as u32)?;
+            images.push(slice(data, offset, size)?);
+            offset += size;
+        }
+    }
+
+    Ok(CompressedImages{
+        format,
+        width,
+        height,
+        layers,
+        faces,
+        levels
+    })
+}
+
+impl Texture{
+    /// Creates a texture from the contents of a KTX2 file, uploading every mip level, face and layer.
+    pub fn from_ktx2(data: &[u8]) -> Result<Self, CompressedTextureError>{
+        parse_ktx2(data)?.upload()
+    }
+
+    pub fn from_ktx2_file(path: impl AsRef<Path>) -> Result<Self, CompressedTextureError>{
+        Self::from_ktx2(&fs::read(path)?)
+    }
+
+    /// Creates a texture from the contents of a DDS file, uploading every mip level, face and layer.
+    pub fn from_dds(data: &[u8]) -> Result<Self, CompressedTextureError>{
+        parse_dds(data)?.upload()
+    }
+
+    pub fn from_dds_file(path: impl AsRef<Path>) -> Result<Self, CompressedTextureError>{
+        Self::from_dds(&fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn put_u32(data: &mut [u8], offset: usize, x: u32){
+        data[offset..offset + 4].copy_from_slice(&x.to_le_bytes());
+    }
+
+    /// A BC4 KTX2 file with the given level sizes, the level data filled with the level index.
+    fn ktx2(width: u32, height: u32, level_count: u32, level_sizes: &[usize]) -> Vec<u8>{
+        let mut data = vec![0; 80 + 24 * level_sizes.len()];
+        data[..12].copy_from_slice(&KTX2_IDENTIFIER);
+        put_u32(&mut data, 12, 139);
+        put_u32(&mut data, 20, width);
+        put_u32(&mut data, 24, height);
+        put_u32(&mut data, 36, 1);
+        put_u32(&mut data, 40, level_count);
+        for (level, &size) in level_sizes.iter().enumerate(){
+            let index = 80 + level * 24;
+            let offset = data.len() as u64;
+            data[index..index + 8].copy_from_slice(&offset.to_le_bytes());
+            data[index + 8..index + 16].copy_from_slice(&(size as u64).to_le_bytes());
+            data.extend(std::iter::repeat_n(level as u8, size));
+        }
+        data
+    }
+
+    /// A DXT1 DDS header with a mip count without any image data.
+    fn dds_header(width: u32, height: u32, level_count: u32) -> Vec<u8>{
+        let mut data = vec![0; 128];
+        data[..4].copy_from_slice(b"DDS ");
+        put_u32(&mut data, 4, 124);
+        put_u32(&mut data, 8, 0x20000);
+        put_u32(&mut data, 12, height);
+        put_u32(&mut data, 16, width);
+        put_u32(&mut data, 28, level_count);
+        put_u32(&mut data, 80, 0x4);
+        data[84..88].copy_from_slice(b"DXT1");
+        data
+    }
+
+    #[test]
+    fn image_size(){
+        let bc1 = CompressedFormat::from_four_cc(b"DXT1").unwrap();
+        assert_eq!(bc1.image_size(1, 1), Some(8));
+        assert_eq!(bc1.image_size(5, 8), Some(32));
+        let astc = CompressedFormat::from_vk_format(183).unwrap();
+        assert_eq!(astc.image_size(13, 12), Some(32));
+        let wide = CompressedFormat{ block_bytes: u32::MAX, ..bc1 };
+        assert_eq!(wide.image_size(u32::MAX, u32::MAX), None);
+    }
+
+    #[test]
+    fn ktx2_levels(){
+        let data = ktx2(8, 8, 4, &[32, 8, 8, 8]);
+        let images = parse_ktx2(&data).unwrap();
+        assert_eq!((images.width, images.height, images.layers, images.faces), (8, 8, 0, 1));
+        assert_eq!(images.levels.len(), 4);
+        for (level, images) in images.levels.iter().enumerate(){
+            assert_eq!(images.len(), 1);
+            assert!(images[0].iter().all(|&x| x == level as u8));
+        }
+    }
+
+    #[test]
+    fn ktx2_rejects_bad_headers(){
+        let too_many_levels = ktx2(8, 8, 5, &[32, 8, 8, 8, 8]);
+        assert!(matches!(parse_ktx2(&too_many_levels), Err(CompressedTextureError::Malformed(_))));
+
+        let huge_level_count = ktx2(8, 8, u32::MAX, &[32]);
+        assert!(matches!(parse_ktx2(&huge_level_count), Err(CompressedTextureError::Malformed(_))));
+
+        let zero_width = ktx2(0, 8, 1, &[8]);
+        assert!(matches!(parse_ktx2(&zero_width), Err(CompressedTextureError::Malformed(_))));
+
+        let wrong_size = ktx2(8, 8, 1, &[24]);
+        assert!(matches!(parse_ktx2(&wrong_size), Err(CompressedTextureError::Malformed(_))));
+
+        let mut too_many_layers = ktx2(8, 8, 1, &[32]);
+        put_u32(&mut too_many_layers, 32, u32::MAX);
+        put_u32(&mut too_many_layers, 36, 6);
+        assert!(matches!(parse_ktx2(&too_many_layers), Err(CompressedTextureError::Malformed(_))));
+
+        let mut huge_layer_count = ktx2(8, 8, 1, &[32]);
+        put_u32(&mut huge_layer_count, 32, 1 << 31);
+        assert!(matches!(parse_ktx2(&huge_layer_count), Err(CompressedTextureError::Malformed(_))));
+
+        let mut truncated = ktx2(8, 8, 1, &[32]);
+        truncated.truncate(truncated.len() - 1);
+        assert!(matches!(parse_ktx2(&truncated), Err(CompressedTextureError::Truncated)));
+
+        let mut supercompressed = ktx2(8, 8, 1, &[32]);
+        put_u32(&mut supercompressed, 44, 1);
+        assert!(matches!(parse_ktx2(&supercompressed), Err(CompressedTextureError::Unsupported(_))));
+    }
+
+    #[test]
+    fn dds_levels(){
+        let mut data = dds_header(8, 4, 3);
+        data.extend(std::iter::repeat_n(0, 16));
+        data.extend(std::iter::repeat_n(1, 8));
+        data.extend(std::iter::repeat_n(2, 8));
+        let images = parse_dds(&data).unwrap();
+        assert_eq!((images.width, images.height, images.layers, images.faces), (8, 4, 0, 1));
+        let sizes: Vec<_> = images.levels.iter().map(|x| x[0].len()).collect();
+        assert_eq!(sizes, [16, 8, 8]);
+        assert!(images.levels[2][0].iter().all(|&x| x == 2));
+    }
+
+    #[test]
+    fn dds_ignores_mip_count_without_flag(){
+        let mut data = dds_header(8, 4, 3);
+        put_u32(&mut data, 8, 0);
+        data.extend(std::iter::repeat_n(0, 16));
+        let images = parse_dds(&data).unwrap();
+        assert_eq!(images.levels.len(), 1);
+    }
+
+    #[test]
+    fn dds_cube_orders_images_by_level(){
+        let mut data = dds_header(4, 4, 2);
+        put_u32(&mut data, 112, 0x200 | 0xFC00);
+        for face in 0..6{
+            data.extend(std::iter::repeat_n(face * 2, 8));
+            data.extend(std::iter::repeat_n(face * 2 + 1, 8));
+        }
+        let images = parse_dds(&data).unwrap();
+        assert_eq!(images.faces, 6);
+        for (level, images) in images.levels.iter().enumerate(){
+            assert_eq!(images.len(), 6);
+            for (face, image) in images.iter().enumerate(){
+                assert_eq!(image[0] as usize, face * 2 + level);
+            }
+        }
+    }
+
+    #[test]
+    fn dds_rejects_bad_headers(){
+        let huge_level_count = dds_header(8, 8, u32::MAX);
+        assert!(matches!(parse_dds(&huge_level_count), Err(CompressedTextureError::Malformed(_))));
+
+        let zero_height = dds_header(8, 0, 1);
+        assert!(matches!(parse_dds(&zero_height), Err(CompressedTextureError::Malformed(_))));
+
+        let huge_width = dds_header(1 << 31, 1, 1);
+        assert!(matches!(parse_dds(&huge_width), Err(CompressedTextureError::Malformed(_))));
+
+        let truncated = dds_header(8, 8, 1);
+        assert!(matches!(parse_dds(&truncated), Err(CompressedTextureError::Truncated)));
+
+        let mut uncompressed = dds_header(8, 8, 1);
+        put_u32(&mut uncompressed, 80, 0x40);
+        assert!(matches!(parse_dds(&uncompressed), Err(CompressedTextureError::UnsupportedFormat(_))));
+    }
+}
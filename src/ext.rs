@@ -0,0 +1,82 @@
+//! Entry points newer than the GL 4.5 core the `gl` crate is generated for,
+//! loaded alongside it in [`crate::load_with`].
+
+use std::{ffi::CStr, mem::transmute, os::raw::{c_char, c_void}, ptr::null_mut, sync::atomic::{AtomicPtr, AtomicU8, Ordering}};
+
+pub(crate) const SHADER_BINARY_FORMAT_SPIR_V: u32 = 0x9551;
+pub(crate) const COMPLETION_STATUS: u32 = 0x91B1;
+pub(crate) const TEXTURE_MAX_ANISOTROPY: u32 = 0x84FE;
+pub(crate) const MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FF;
+
+type SpecializeShaderFn = extern "system" fn(u32, *const c_char, u32, *const u32, *const u32);
+type MaxShaderCompilerThreadsFn = extern "system" fn(u32);
+
+static SPECIALIZE_SHADER: AtomicPtr<c_void> = AtomicPtr::new(null_mut());
+static MAX_SHADER_COMPILER_THREADS: AtomicPtr<c_void> = AtomicPtr::new(null_mut());
+
+/// 0 until checked, then 1 or 2 for parallel shader compile being unsupported or supported.
+static PARALLEL_COMPILE: AtomicU8 = AtomicU8::new(0);
+
+pub(crate) fn load_with<F>(mut load: F)
+    where F: FnMut(&'static str) -> *const c_void
+{
+    let mut load_any = |names: &[&'static str]| {
+        names.iter()
+            .map(|name| load(name))
+            .find(|ptr| !ptr.is_null())
+            .unwrap_or(std::ptr::null()) as *mut c_void
+    };
+
+    SPECIALIZE_SHADER.store(load_any(&["glSpecializeShader", "glSpecializeShaderARB"]), Ordering::Relaxed);
+    MAX_SHADER_COMPILER_THREADS.store(load_any(&["glMaxShaderCompilerThreadsKHR", "glMaxShaderCompilerThreadsARB"]), Ordering::Relaxed);
+    PARALLEL_COMPILE.store(0, Ordering::Relaxed);
+}
+
+/// Checks the extension list of the current context.
+pub(crate) fn has_extension(name: &str) -> bool{
+    unsafe{
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        (0..count as u32).any(|i| {
+            let ptr = gl::GetStringi(gl::EXTENSIONS, i);
+            !ptr.is_null() && CStr::from_ptr(ptr.cast()).to_bytes() == name.as_bytes()
+        })
+    }
+}
+
+/// True if KHR or ARB_parallel_shader_compile allow polling `COMPLETION_STATUS`.
+pub(crate) fn supports_parallel_compile() -> bool{
+    match PARALLEL_COMPILE.load(Ordering::Relaxed){
+        0 => {
+            let supported = has_extension("GL_KHR_parallel_shader_compile") || has_extension("GL_ARB_parallel_shader_compile");
+            PARALLEL_COMPILE.store(if supported { 2 } else { 1 }, Ordering::Relaxed);
+            supported
+        },
+        x => x == 2
+    }
+}
+
+/// `glMaxShaderCompilerThreadsKHR`, does nothing without parallel shader compile.
+pub(crate) fn max_shader_compiler_threads(count: u32){
+    let ptr = MAX_SHADER_COMPILER_THREADS.load(Ordering::Relaxed);
+    if ptr.is_null() || !supports_parallel_compile(){
+        return;
+    }
+    unsafe{
+        let f: MaxShaderCompilerThreadsFn = transmute(ptr);
+        f(count);
+    }
+}
+
+/// True if GL 4.6 or ARB_gl_spirv provided `glSpecializeShader`.
+pub(crate) fn supports_spirv() -> bool{
+    !SPECIALIZE_SHADER.load(Ordering::Relaxed).is_null()
+}
+
+/// `glSpecializeShader`, panics if [`supports_spirv`] is false.
+pub(crate) unsafe fn specialize_shader(shader: u32, entry_point: *const c_char, count: u32, indices: *const u32, values: *const u32){
+    let ptr = SPECIALIZE_SHADER.load(Ordering::Relaxed);
+    assert!(!ptr.is_null(), "glSpecializeShader isn't loaded");
+    let f: SpecializeShaderFn = transmute(ptr);
+    f(shader, entry_point, count, indices, values);
+}
@@ -0,0 +1,310 @@
+use std::{error::Error, fmt::{self, Display, Formatter}, rc::Rc};
+
+use crate::{internal::{RawId, RawIdManager}, pixel::{self, PendingReadback, Pixel, ReadError, Region}, renderbuffer::Renderbuffer, texture::{Texture, UnsupportedSamples}};
+
+#[derive(Clone)]
+enum Attachment{
+    Texture(Texture),
+    Renderbuffer(Renderbuffer),
+}
+
+impl Attachment{
+    fn size(&self) -> (i32, i32){
+        match self{
+            Attachment::Texture(x) => (x.width(), x.height()),
+            Attachment::Renderbuffer(x) => (x.width(), x.height()),
+        }
+    }
+
+    fn samples(&self) -> i32{
+        match self{
+            Attachment::Texture(x) => x.samples(),
+            Attachment::Renderbuffer(x) => x.samples(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Framebuffer{
+    id: Rc<RawId<Self>>,
+    /// Keeps attached textures and renderbuffers alive while they're in use.
+    attachments: Vec<(u32, Attachment)>,
+}
+
+impl Framebuffer{
+    pub fn new() -> Self{
+        Self{
+            id: Rc::new(RawId::new()),
+            attachments: Vec::new()
+        }
+    }
+
+    /// The window's framebuffer, read with `gl::BACK` or `gl::FRONT`.
+    pub fn default_framebuffer() -> Self{
+        Self{
+            // deleting framebuffer 0 is ignored
+            id: Rc::new(unsafe { RawId::from_id(0) }),
+            attachments: Vec::new()
+        }
+    }
+
+    /// A framebuffer rendering to multisampled renderbuffers, with a color attachment of
+    /// `color_format` and optionally a depth, stencil or depth stencil attachment of `depth_format`.
+    pub fn new_multisample(width: i32, height: i32, samples: i32, color_format: u32, depth_format: Option<u32>) -> Result<Self, FramebufferError>{
+        let mut out = Self::new();
+        out.attach_renderbuffer(gl::COLOR_ATTACHMENT0, &Renderbuffer::new_multisample(color_format, width, height, samples)?);
+        if let Some(depth_format) = depth_format{
+            let attachment = match depth_format{
+                gl::DEPTH24_STENCIL8 | gl::DEPTH32F_STENCIL8 => gl::DEPTH_STENCIL_ATTACHMENT,
+                gl::STENCIL_INDEX1 | gl::STENCIL_INDEX4 | gl::STENCIL_INDEX8 | gl::STENCIL_INDEX16 => gl::STENCIL_ATTACHMENT,
+                _ => gl::DEPTH_ATTACHMENT
+            };
+            out.attach_renderbuffer(attachment, &Renderbuffer::new_multisample(depth_format, width, height, samples)?);
+        }
+        out.check_status()?;
+        Ok(out)
+    }
+
+    /// Attaches mip `level` of `texture` to `attachment`, like `gl::COLOR_ATTACHMENT0`.
+    pub fn attach_texture(&mut self, attachment: u32, texture: &Texture, level: i32){
+        unsafe{
+            gl::NamedFramebufferTexture(self.id(), attachment, texture.id(), level);
+        }
+        self.set_attachment(attachment, Attachment::Texture(texture.clone()));
+    }
+
+    /// Attaches a single layer or cube map face of `texture`.
+    pub fn attach_texture_layer(&mut self, attachment: u32, texture: &Texture, level: i32, layer: i32){
+        unsafe{
+            gl::NamedFramebufferTextureLayer(self.id(), attachment, texture.id(), level, layer);
+        }
+        self.set_attachment(attachment, Attachment::Texture(texture.clone()));
+    }
+
+    pub fn attach_renderbuffer(&mut self, attachment: u32, renderbuffer: &Renderbuffer){
+        unsafe{
+            gl::NamedFramebufferRenderbuffer(self.id(), attachment, gl::RENDERBUFFER, renderbuffer.id());
+        }
+        self.set_attachment(attachment, Attachment::Renderbuffer(renderbuffer.clone()));
+    }
+
+    fn set_attachment(&mut self, attachment: u32, target: Attachment){
+        self.attachments.retain(|(x, _)| *x != attachment);
+        self.attachments.push((attachment, target));
+    }
+
+    pub fn detach(&mut self, attachment: u32){
+        unsafe{
+            // works for renderbuffers too, both clear the attachment
+            gl::NamedFramebufferTexture(self.id(), attachment, 0, 0);
+        }
+        self.attachments.retain(|(x, _)| *x != attachment);
+    }
+
+    /// Selects the color attachments fragment outputs are written to.
+    pub fn set_draw_buffers(&self, buffers: &[u32]){
+        unsafe{
+            gl::NamedFramebufferDrawBuffers(self.id(), buffers.len() as _, buffers.as_ptr());
+        }
+    }
+
+    /// Fails with the status for incomplete framebuffers, like
+    /// `gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE` for attachments with different sample counts.
+    pub fn check_status(&self) -> Result<(), FramebufferError>{
+        unsafe{
+            match gl::CheckNamedFramebufferStatus(self.id(), gl::FRAMEBUFFER){
+                gl::FRAMEBUFFER_COMPLETE => Ok(()),
+                status => Err(FramebufferError::Incomplete(status))
+            }
+        }
+    }
+
+    /// Size of the first attachment, `None` for the default framebuffer or without attachments.
+    pub fn size(&self) -> Option<(i32, i32)>{
+        self.attachments.first().map(|(_, x)| x.size())
+    }
+
+    /// Samples per pixel of the first attachment, or of the window for the default framebuffer.
+    /// 0 for single sampled framebuffers.
+    pub fn samples(&self) -> i32{
+        match self.attachments.first(){
+            Some((_, x)) => x.samples(),
+            None => unsafe{
+                let mut samples = 0;
+                gl::GetNamedFramebufferParameteriv(self.id(), gl::SAMPLES, &mut samples);
+                samples
+            }
+        }
+    }
+
+    /// Copies `src` of this framebuffer to `dst` of `target`, scaling with `filter`.
+    ///
+    /// `mask` combines `gl::COLOR_BUFFER_BIT`, `gl::DEPTH_BUFFER_BIT` and `gl::STENCIL_BUFFER_BIT`,
+    /// depth and stencil can only be copied with `gl::NEAREST`.
+    pub fn blit(&self, target: &Framebuffer, src: Region, dst: Region, mask: u32, filter: u32){
+        unsafe{
+            gl::BlitNamedFramebuffer(
+                self.id(), target.id(),
+                src.x, src.y, src.x + src.width, src.y + src.height,
+                dst.x, dst.y, dst.x + dst.width, dst.y + dst.height,
+                mask, filter
+            );
+        }
+    }
+
+    /// Resolves this multisampled framebuffer into the single sampled `target` at the same size.
+    ///
+    /// Reads from the current read buffer and writes to the draw buffers of `target`.
+    /// The size of the default framebuffer isn't known, so it's expected to match.
+    pub fn resolve(&self, target: &Framebuffer, mask: u32) -> Result<(), FramebufferError>{
+        let size = self.size().ok_or(FramebufferError::NoAttachments)?;
+        if let Some(target_size) = target.size(){
+            if target_size != size{
+                return Err(FramebufferError::SizeMismatch{
+                    source: size,
+                    target: target_size
+                });
+            }
+        }
+        let samples = target.samples();
+        if samples > 0{
+            return Err(FramebufferError::MultisampledTarget(samples));
+        }
+        let region = Region::rect(0, 0, size.0, size.1);
+        self.blit(target, region, region, mask, gl::NEAREST);
+        Ok(())
+    }
+
+    /// Copies `region` of the `read_buffer` attachment to the CPU, waiting for rendering to finish.
+    ///
+    /// `read_buffer` is ignored when reading [`Depth`](crate::pixel::Depth). The framebuffer's
+    /// own read buffer is left as it was. Framebuffers are read one layer at a time, so
+    /// `region` needs a `z` of 0 and a `depth` of 1.
+    pub fn read_pixels<T: Pixel>(&self, read_buffer: u32, region: Region) -> Result<Vec<T>, ReadError>{
+        check_flat(&region)?;
+        let size = pixel::read_size::<T>(&region)?;
+        let mut out = vec![T::default(); region.len()];
+        pixel::check_read(|| self.with_read_binding(color_read_buffer::<T>(read_buffer), || unsafe{
+            gl::ReadnPixels(region.x, region.y, region.width, region.height, T::FORMAT, T::TYPE, size as _, out.as_mut_ptr().cast());
+        }))?;
+        Ok(out)
+    }
+
+    /// Starts copying `region` into a pixel pack buffer without waiting.
+    pub fn read_pixels_async<T: Pixel>(&self, read_buffer: u32, region: Region) -> Result<PendingReadback<T>, ReadError>{
+        check_flat(&region)?;
+        PendingReadback::new(&region, |size| {
+            self.with_read_binding(color_read_buffer::<T>(read_buffer), || unsafe{
+                gl::ReadnPixels(region.x, region.y, region.width, region.height, T::FORMAT, T::TYPE, size as _, std::ptr::null_mut());
+            })
+        })
+    }
+
+    /// Runs `f` with this framebuffer bound for reading from `read_buffer`, restoring the
+    /// previous binding and read buffer after.
+    fn with_read_binding(&self, read_buffer: Option<u32>, f: impl FnOnce()){
+        unsafe{
+            let mut previous = 0;
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut previous);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id());
+            let mut previous_read_buffer = 0;
+            gl::GetIntegerv(gl::READ_BUFFER, &mut previous_read_buffer);
+            if let Some(read_buffer) = read_buffer{
+                gl::NamedFramebufferReadBuffer(self.id(), read_buffer);
+            }
+            pixel::with_pack_alignment(f);
+            if read_buffer.is_some(){
+                gl::NamedFramebufferReadBuffer(self.id(), previous_read_buffer as u32);
+            }
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, previous as u32);
+        }
+    }
+
+    pub fn bind(&self){
+        unsafe{
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id());
+        }
+    }
+
+    pub(crate) fn id(&self) -> u32{
+        self.id.id()
+    }
+}
+
+/// ReadnPixels only reads `width * height` pixels of the first layer.
+fn check_flat(region: &Region) -> Result<(), ReadError>{
+    if region.z != 0 || region.depth != 1{
+        return Err(ReadError::Size(*region));
+    }
+    Ok(())
+}
+
+/// Depth reads don't go through the read buffer.
+fn color_read_buffer<T: Pixel>(read_buffer: u32) -> Option<u32>{
+    (T::FORMAT != gl::DEPTH_COMPONENT).then_some(read_buffer)
+}
+
+impl Default for Framebuffer{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RawIdManager for Framebuffer{
+    fn create_resource() -> u32 {
+        unsafe{
+            let mut i = 0;
+            gl::CreateFramebuffers(1, &mut i);
+            i
+        }
+    }
+
+    fn delete_resource(id: u32) {
+        unsafe{
+            gl::DeleteFramebuffers(1, &id);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramebufferError{
+    /// The status `glCheckNamedFramebufferStatus` returned.
+    Incomplete(u32),
+    Samples(UnsupportedSamples),
+    /// The framebuffer has nothing attached to read from.
+    NoAttachments,
+    /// A resolve needs both framebuffers to have the same size.
+    SizeMismatch{
+        source: (i32, i32),
+        target: (i32, i32)
+    },
+    /// The target of a resolve has this many samples instead of being single sampled.
+    MultisampledTarget(i32),
+}
+
+impl Display for FramebufferError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self{
+            FramebufferError::Incomplete(status) => write!(f, "Framebuffer is incomplete, status 0x{:X}", status),
+            FramebufferError::Samples(x) => Display::fmt(x, f),
+            FramebufferError::NoAttachments => write!(f, "Framebuffer has no attachments"),
+            FramebufferError::SizeMismatch { source, target } => write!(f, "Can't resolve a {}x{} framebuffer into {}x{}", source.0, source.1, target.0, target.1),
+            FramebufferError::MultisampledTarget(samples) => write!(f, "Can't resolve into a framebuffer with {} samples", samples),
+        }
+    }
+}
+
+impl Error for FramebufferError{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self{
+            FramebufferError::Samples(x) => Some(x),
+            _ => None
+        }
+    }
+}
+
+impl From<UnsupportedSamples> for FramebufferError{
+    fn from(x: UnsupportedSamples) -> Self {
+        Self::Samples(x)
+    }
+}
@@ -0,0 +1,274 @@
+//! Loading PNG, JPEG, TGA and HDR files into textures, enabled with the `image` feature.
+
+use std::{error::Error, f32::consts::PI, fmt::{self, Display, Formatter}, path::Path};
+
+use image::{DynamicImage, ImageError, Rgba32FImage};
+
+use crate::texture::Texture;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextureLoadOptions{
+    /// Store 8 bit color data in an sRGB format so sampling returns linear values.
+    pub srgb: bool,
+    /// Flip rows so the first row of the file ends up at the bottom, as GL expects.
+    /// Ignored for cube maps, whose faces are stored top row first.
+    pub flip_y: bool,
+    /// Allocate and generate the full mip chain.
+    pub mipmaps: bool,
+}
+
+impl Default for TextureLoadOptions{
+    fn default() -> Self {
+        Self{
+            srgb: false,
+            flip_y: true,
+            mipmaps: true
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TextureLoadError{
+    Image(ImageError),
+    /// The six faces of a cube map have to be square and equally sized.
+    FaceSize{
+        face: usize,
+        width: u32,
+        height: u32
+    },
+    /// The six faces of a cube map have to decode to the same internal format.
+    FaceFormat{
+        face: usize,
+        internal_format: u32,
+        expected: u32
+    },
+    /// The requested cube map face size is 0 or too large for GL.
+    InvalidFaceSize(u32),
+}
+
+impl Display for TextureLoadError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self{
+            TextureLoadError::Image(e) => Display::fmt(e, f),
+            TextureLoadError::FaceSize { face, width, height } => write!(f, "Cube map face {} is {}x{}, faces have to be square and the same size", face, width, height),
+            TextureLoadError::FaceFormat { face, internal_format, expected } => write!(f, "Cube map face {} decodes to format 0x{:X}, the first face to 0x{:X}", face, internal_format, expected),
+            TextureLoadError::InvalidFaceSize(x) => write!(f, "Invalid cube map face size {}", x),
+        }
+    }
+}
+
+impl Error for TextureLoadError{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self{
+            TextureLoadError::Image(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<ImageError> for TextureLoadError{
+    fn from(x: ImageError) -> Self {
+        Self::Image(x)
+    }
+}
+
+/// Decoded pixels in the layout they are uploaded with.
+enum PixelData{
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    F32(Vec<f32>),
+}
+
+struct Pixels{
+    width: i32,
+    height: i32,
+    internal_format: u32,
+    format: u32,
+    type_: u32,
+    data: PixelData,
+}
+
+impl Pixels{
+    fn new(image: DynamicImage, options: &TextureLoadOptions) -> Self{
+        let image = if options.flip_y { image.flipv() } else { image };
+        let (width, height) = (image.width() as i32, image.height() as i32);
+
+        match image{
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => Self{
+                width,
+                height,
+                internal_format: gl::RGBA16F,
+                format: gl::RGBA,
+                type_: gl::FLOAT,
+                data: PixelData::F32(image.into_rgba32f().into_raw())
+            },
+            DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_) | DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_) => Self{
+                width,
+                height,
+                // there are no 16 bit sRGB formats
+                internal_format: gl::RGBA16,
+                format: gl::RGBA,
+                type_: gl::UNSIGNED_SHORT,
+                data: PixelData::U16(image.into_rgba16().into_raw())
+            },
+            image => Self{
+                width,
+                height,
+                internal_format: if options.srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 },
+                format: gl::RGBA,
+                type_: gl::UNSIGNED_BYTE,
+                data: PixelData::U8(image.into_rgba8().into_raw())
+            }
+        }
+    }
+
+    fn upload(&self, texture: &Texture, layer: i32){
+        let Self { width, height, format, type_, .. } = *self;
+        match &self.data{
+            PixelData::U8(data) => texture.set_sub_image_3d(0, 0, 0, layer, width, height, 1, format, type_, data),
+            PixelData::U16(data) => texture.set_sub_image_3d(0, 0, 0, layer, width, height, 1, format, type_, data),
+            PixelData::F32(data) => texture.set_sub_image_3d(0, 0, 0, layer, width, height, 1, format, type_, data),
+        }
+    }
+}
+
+fn levels(width: i32, height: i32, options: &TextureLoadOptions) -> i32{
+    if options.mipmaps { Texture::mip_levels(width, height) } else { 1 }
+}
+
+fn finish(texture: Texture, options: &TextureLoadOptions) -> Texture{
+    if options.mipmaps{
+        texture.generate_mipmaps();
+    }
+    texture
+}
+
+impl Texture{
+    /// Loads an image file into a 2D texture, the format is detected from the contents.
+    pub fn from_file(path: impl AsRef<Path>, options: &TextureLoadOptions) -> Result<Self, TextureLoadError>{
+        Ok(Self::from_image(image::open(path)?, options))
+    }
+
+    pub fn from_memory(data: &[u8], options: &TextureLoadOptions) -> Result<Self, TextureLoadError>{
+        Ok(Self::from_image(image::load_from_memory(data)?, options))
+    }
+
+    pub fn from_image(image: DynamicImage, options: &TextureLoadOptions) -> Self{
+        let pixels = Pixels::new(image, options);
+        let texture = Self::new_2d(pixels.internal_format, pixels.width, pixels.height, levels(pixels.width, pixels.height, options));
+        pixels.upload(&texture, 0);
+        finish(texture, options)
+    }
+
+    /// Loads a cube map from six files in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cube_from_files<P: AsRef<Path>>(paths: [P; 6], options: &TextureLoadOptions) -> Result<Self, TextureLoadError>{
+        let face_options = TextureLoadOptions{
+            flip_y: false,
+            ..*options
+        };
+        let mut faces = Vec::with_capacity(6);
+        for path in paths{
+            faces.push(Pixels::new(image::open(path)?, &face_options));
+        }
+
+        let size = faces[0].width;
+        for (face, pixels) in faces.iter().enumerate(){
+            if pixels.width != size || pixels.height != size{
+                return Err(TextureLoadError::FaceSize{
+                    face,
+                    width: pixels.width as u32,
+                    height: pixels.height as u32
+                });
+            }
+            if pixels.internal_format != faces[0].internal_format{
+                return Err(TextureLoadError::FaceFormat{
+                    face,
+                    internal_format: pixels.internal_format,
+                    expected: faces[0].internal_format
+                });
+            }
+        }
+
+        let texture = Self::new_cube(faces[0].internal_format, size, levels(size, size, options));
+        for (face, pixels) in faces.iter().enumerate(){
+            pixels.upload(&texture, face as i32);
+        }
+        Ok(finish(texture, options))
+    }
+
+    /// Loads a cube map with faces of `face_size` from an equirectangular (latitude/longitude) image.
+    pub fn cube_from_equirectangular(path: impl AsRef<Path>, face_size: u32, options: &TextureLoadOptions) -> Result<Self, TextureLoadError>{
+        let size = match i32::try_from(face_size){
+            Ok(x) if x > 0 => x,
+            _ => return Err(TextureLoadError::InvalidFaceSize(face_size))
+        };
+        let image = image::open(path)?;
+        let hdr = matches!(image, DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_));
+        let source = image.to_rgba32f();
+
+        let internal_format = match (hdr, options.srgb){
+            (true, _) => gl::RGBA16F,
+            (false, true) => gl::SRGB8_ALPHA8,
+            (false, false) => gl::RGBA8,
+        };
+        let texture = Self::new_cube(internal_format, size, levels(size, size, options));
+
+        let mut face_data = vec![0.0f32; face_size as usize * face_size as usize * 4];
+        for face in 0..6{
+            for y in 0..face_size{
+                for x in 0..face_size{
+                    let s = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+                    let t = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+                    let color = sample_equirectangular(&source, cube_direction(face, s, t));
+                    let i = (y as usize * face_size as usize + x as usize) * 4;
+                    face_data[i..i + 4].copy_from_slice(&color);
+                }
+            }
+            texture.set_sub_image_3d(0, 0, 0, face as i32, size, size, 1, gl::RGBA, gl::FLOAT, &face_data);
+        }
+        Ok(finish(texture, options))
+    }
+}
+
+/// Direction through the texel at `s`, `t` in [-1, 1] of a cube map face, following the
+/// face orientations of the GL spec.
+fn cube_direction(face: u32, s: f32, t: f32) -> [f32; 3]{
+    let dir = match face{
+        0 => [1.0, -t, -s],
+        1 => [-1.0, -t, s],
+        2 => [s, 1.0, t],
+        3 => [s, -1.0, -t],
+        4 => [s, -t, 1.0],
+        _ => [-s, -t, -1.0],
+    };
+    let len = (dir[0] * dir[0] + dir[1] * dir[1] + dir[2] * dir[2]).sqrt();
+    [dir[0] / len, dir[1] / len, dir[2] / len]
+}
+
+/// Bilinear sample of an equirectangular image in direction `dir`, wrapping horizontally.
+fn sample_equirectangular(image: &Rgba32FImage, dir: [f32; 3]) -> [f32; 4]{
+    let (width, height) = (image.width(), image.height());
+    let u = 0.5 + dir[2].atan2(dir[0]) / (2.0 * PI);
+    let v = dir[1].clamp(-1.0, 1.0).acos() / PI;
+
+    let x = u * width as f32 - 0.5;
+    let y = (v * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+
+    let texel = |x: f32, y: f32| {
+        let x = (x as i64).rem_euclid(width as i64) as u32;
+        let y = (y as u32).min(height - 1);
+        image.get_pixel(x, y).0
+    };
+    let (a, b) = (texel(x0, y0), texel(x0 + 1.0, y0));
+    let (c, d) = (texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0));
+
+    let mut out = [0.0; 4];
+    for i in 0..4{
+        let top = a[i] + (b[i] - a[i]) * fx;
+        let bottom = c[i] + (d[i] - c[i]) * fx;
+        out[i] = top + (bottom - top) * fy;
+    }
+    out
+}
@@ -3,13 +3,32 @@
 pub use paste;
 pub use gl;
 pub(crate) mod internal;
+pub(crate) mod ext;
 pub mod buffer;
 pub mod vao;
 pub mod shader;
+pub mod pipeline;
+pub mod parallel;
+pub mod preprocessor;
+pub mod reload;
+pub mod program_cache;
+pub mod texture;
+pub mod compressed;
+pub mod sampler;
+pub mod pixel;
+pub mod framebuffer;
+pub mod renderbuffer;
+pub mod query;
+pub mod profiler;
+pub mod sync;
+pub mod transform_feedback;
+#[cfg(feature = "image")]
+pub mod image_loader;
 use std::os::raw::c_void;
 
-pub fn load_with<F>(x: F)
+pub fn load_with<F>(mut x: F)
     where F: FnMut(&'static str) -> *const c_void
 {
-    gl::load_with(x);
+    gl::load_with(&mut x);
+    ext::load_with(x);
 }
\ No newline at end of file
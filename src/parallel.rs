@@ -0,0 +1,66 @@
+use std::{rc::Rc, task::Poll};
+
+use crate::{ext, internal::RawId, shader::{Program, Shader, ShaderError}};
+
+/// A program whose shaders were submitted for compiling and linking without waiting for
+/// the driver, created with [`ProgramBuilder::build_async`](crate::shader::ProgramBuilder::build_async).
+///
+/// With `GL_KHR_parallel_shader_compile` the driver compiles in the background and
+/// [`PendingProgram::poll`] doesn't block. Without it, polling waits for the driver.
+pub struct PendingProgram{
+    state: Option<(Rc<RawId<Program>>, Vec<Shader>)>,
+}
+
+impl PendingProgram{
+    pub(crate) fn new(id: Rc<RawId<Program>>, shaders: Vec<Shader>) -> Self{
+        Self{
+            state: Some((id, shaders))
+        }
+    }
+
+    /// True if resolving won't block, always true without parallel shader compile.
+    pub fn is_ready(&self) -> bool{
+        let (id, _) = self.state.as_ref().expect("PendingProgram polled after completion");
+        if !ext::supports_parallel_compile(){
+            return true;
+        }
+        unsafe{
+            let mut done = 0;
+            gl::GetProgramiv(id.id(), ext::COMPLETION_STATUS, &mut done);
+            done != gl::FALSE as _
+        }
+    }
+
+    /// Returns the program once the driver is done with it.
+    ///
+    /// Panics if called again after returning `Poll::Ready`.
+    pub fn poll(&mut self) -> Poll<Result<Program, ShaderError>>{
+        if !self.is_ready(){
+            return Poll::Pending;
+        }
+        let (id, shaders) = self.state.take().unwrap();
+        Poll::Ready(Self::finish(id, shaders))
+    }
+
+    /// Waits for the driver and returns the program.
+    pub fn resolve(mut self) -> Result<Program, ShaderError>{
+        let (id, shaders) = self.state.take().expect("PendingProgram polled after completion");
+        Self::finish(id, shaders)
+    }
+
+    fn finish(id: Rc<RawId<Program>>, shaders: Vec<Shader>) -> Result<Program, ShaderError>{
+        // the compile log explains a failed link better than the link log
+        for shader in &shaders{
+            shader.compile_status()?;
+        }
+        Program::from_linked(id, shaders)
+    }
+}
+
+/// Sets how many threads the driver may use to compile shaders, `u32::MAX` lets it
+/// decide and 0 compiles on the calling thread.
+///
+/// Does nothing without `GL_KHR_parallel_shader_compile`.
+pub fn set_max_compiler_threads(count: u32){
+    ext::max_shader_compiler_threads(count);
+}
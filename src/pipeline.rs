@@ -0,0 +1,129 @@
+use std::rc::Rc;
+
+use crate::{internal::{RawId, RawIdManager}, shader::{Program, ShaderError, UniformContainer}, texture::TextureUnits};
+
+/// Something [`VertexArray::draw`](crate::vao::VertexArray::draw) can render with,
+/// either a [`Program`] or a [`ProgramPipeline`].
+pub trait ShaderProgram{
+    fn bind(&self);
+
+    /// Writes `uniforms` to every program involved and binds their textures.
+    fn bind_uniforms(&self, uniforms: &dyn UniformContainer);
+}
+
+impl ShaderProgram for Program{
+    fn bind(&self){
+        Program::bind(self);
+    }
+
+    fn bind_uniforms(&self, uniforms: &dyn UniformContainer){
+        uniforms.bind(self, &mut TextureUnits::new());
+    }
+}
+
+/// Combines the stages of separable programs at draw time.
+#[derive(Clone)]
+pub struct ProgramPipeline{
+    id: Rc<RawId<Self>>,
+    /// The programs in use and the stages they provide.
+    programs: Vec<(u32, Program)>,
+}
+
+impl ProgramPipeline{
+    pub fn new() -> Self{
+        Self{
+            id: Rc::new(RawId::new()),
+            programs: Vec::new()
+        }
+    }
+
+    /// Uses `program` for `stages`, a mask of `gl::VERTEX_SHADER_BIT`, `gl::FRAGMENT_SHADER_BIT`, ...
+    ///
+    /// The program has to be linked with [`ProgramBuilder::separable`](crate::shader::ProgramBuilder::separable).
+    pub fn use_stages(&mut self, stages: u32, program: &Program){
+        unsafe{
+            gl::UseProgramStages(self.id.id(), stages, program.id());
+        }
+        for (program_stages, _) in &mut self.programs{
+            *program_stages &= !stages;
+        }
+        self.programs.retain(|(program_stages, _)| *program_stages != 0);
+        self.programs.push((stages, program.clone()));
+    }
+
+    /// Removes the programs of `stages`.
+    pub fn clear_stages(&mut self, stages: u32){
+        unsafe{
+            gl::UseProgramStages(self.id.id(), stages, 0);
+        }
+        for (program_stages, _) in &mut self.programs{
+            *program_stages &= !stages;
+        }
+        self.programs.retain(|(program_stages, _)| *program_stages != 0);
+    }
+
+    /// Checks that the stages fit together.
+    pub fn validate(&self) -> Result<(), ShaderError>{
+        unsafe{
+            gl::ValidateProgramPipeline(self.id.id());
+            let mut success = 0;
+            gl::GetProgramPipelineiv(self.id.id(), gl::VALIDATE_STATUS, &mut success);
+            if success == gl::FALSE as _{
+                let mut len = 0;
+                gl::GetProgramPipelineiv(self.id.id(), gl::INFO_LOG_LENGTH, &mut len);
+                let mut buf = vec![0u8; len.max(1) as usize];
+                gl::GetProgramPipelineInfoLog(self.id.id(), len, &mut len, buf.as_mut_ptr().cast());
+                return Err(ShaderError::Validate(String::from_utf8_lossy(&buf[0..len as usize]).to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn programs(&self) -> impl Iterator<Item = &Program>{
+        self.programs.iter().map(|(_, program)| program)
+    }
+
+    pub fn bind(&self){
+        unsafe{
+            // a program in use takes precedence over the bound pipeline
+            gl::UseProgram(0);
+            gl::BindProgramPipeline(self.id.id());
+        }
+    }
+}
+
+impl Default for ProgramPipeline{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShaderProgram for ProgramPipeline{
+    fn bind(&self){
+        ProgramPipeline::bind(self);
+    }
+
+    fn bind_uniforms(&self, uniforms: &dyn UniformContainer){
+        // stages sampling the same texture share its unit
+        let mut units = TextureUnits::new();
+        for program in self.programs(){
+            uniforms.bind(program, &mut units);
+        }
+    }
+}
+
+impl RawIdManager for ProgramPipeline{
+    fn create_resource() -> u32 {
+        unsafe{
+            let mut i = 0;
+            gl::CreateProgramPipelines(1, &mut i);
+            i
+        }
+    }
+
+    fn delete_resource(id: u32) {
+        unsafe{
+            gl::DeleteProgramPipelines(1, &id);
+        }
+    }
+}
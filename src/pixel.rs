@@ -0,0 +1,211 @@
+//! Pixel formats and regions for reading texture and framebuffer data back to the CPU.
+
+use std::{error::Error, fmt::{self, Display, Formatter}, marker::PhantomData, mem::size_of, time::Duration};
+
+use glam::Vec4;
+
+use crate::{buffer::Buffer, sync::Fence};
+
+/// A texel type pixels can be transferred as.
+///
+/// `FORMAT` and `TYPE` have to describe exactly `size_of::<Self>()` bytes.
+pub trait Pixel: Copy + Default{
+    const FORMAT: u32;
+    const TYPE: u32;
+}
+
+macro_rules! pixel_impl {
+    ($($t:ty => $format:ident, $type_:ident),*) => {
+        $(
+            impl Pixel for $t{
+                const FORMAT: u32 = gl::$format;
+                const TYPE: u32 = gl::$type_;
+            }
+        )*
+    };
+}
+
+pixel_impl!(
+    u8 => RED, UNSIGNED_BYTE,
+    [u8; 2] => RG, UNSIGNED_BYTE,
+    [u8; 3] => RGB, UNSIGNED_BYTE,
+    [u8; 4] => RGBA, UNSIGNED_BYTE,
+    u16 => RED, UNSIGNED_SHORT,
+    [u16; 4] => RGBA, UNSIGNED_SHORT,
+    f32 => RED, FLOAT,
+    [f32; 2] => RG, FLOAT,
+    [f32; 3] => RGB, FLOAT,
+    [f32; 4] => RGBA, FLOAT,
+    Vec4 => RGBA, FLOAT,
+    i32 => RED_INTEGER, INT,
+    [i32; 4] => RGBA_INTEGER, INT,
+    u32 => RED_INTEGER, UNSIGNED_INT,
+    [u32; 4] => RGBA_INTEGER, UNSIGNED_INT
+);
+
+/// A depth value, for reading depth textures and attachments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[repr(transparent)]
+pub struct Depth(pub f32);
+
+impl Pixel for Depth{
+    const FORMAT: u32 = gl::DEPTH_COMPONENT;
+    const TYPE: u32 = gl::FLOAT;
+}
+
+/// A box of texels, `z` and `depth` select layers or cube map faces for textures that have them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region{
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub width: i32,
+    pub height: i32,
+    pub depth: i32,
+}
+
+impl Region{
+    pub fn rect(x: i32, y: i32, width: i32, height: i32) -> Self{
+        Self{
+            x,
+            y,
+            z: 0,
+            width,
+            height,
+            depth: 1
+        }
+    }
+
+    /// Number of texels in the region, `None` if it doesn't fit a `usize`.
+    pub fn checked_len(&self) -> Option<usize>{
+        [self.width, self.height, self.depth].into_iter()
+            .try_fold(1usize, |len, x| len.checked_mul(x.max(0) as usize))
+    }
+
+    /// Number of texels in the region, panics if it doesn't fit a `usize`.
+    pub fn len(&self) -> usize{
+        self.checked_len().expect("Region size overflows")
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadError{
+    /// The region has a negative size, is too large to read at once, or has more than one
+    /// layer when reading a framebuffer.
+    Size(Region),
+    /// The error `glGetError` reported for the copy, like `gl::INVALID_OPERATION` for a
+    /// pixel type that doesn't match the format being read.
+    Gl(u32),
+}
+
+impl Display for ReadError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self{
+            ReadError::Size(x) => write!(f, "Can't read a region of {}x{}x{} pixels", x.width, x.height, x.depth),
+            ReadError::Gl(x) => write!(f, "Reading pixels failed with error 0x{:X}", x),
+        }
+    }
+}
+
+impl Error for ReadError{}
+
+/// Size of `region` in bytes when read as `T`, which GL takes as an `i32`.
+pub(crate) fn read_size<T: Pixel>(region: &Region) -> Result<usize, ReadError>{
+    if region.width < 0 || region.height < 0 || region.depth < 0{
+        return Err(ReadError::Size(*region));
+    }
+    region.checked_len()
+        .and_then(|x| x.checked_mul(size_of::<T>()))
+        .filter(|x| i32::try_from(*x).is_ok())
+        .ok_or(ReadError::Size(*region))
+}
+
+/// Runs the copy `f` and fails with the error it raised.
+pub(crate) fn check_read(f: impl FnOnce()) -> Result<(), ReadError>{
+    unsafe{
+        // clear errors of earlier calls so they aren't blamed on this one
+        loop{
+            match gl::GetError(){
+                gl::NO_ERROR | gl::CONTEXT_LOST => break,
+                _ => {}
+            }
+        }
+        f();
+        match gl::GetError(){
+            gl::NO_ERROR => Ok(()),
+            e => Err(ReadError::Gl(e))
+        }
+    }
+}
+
+/// Runs `f` with tightly packed rows for pixel transfers to the CPU.
+pub(crate) fn with_pack_alignment<R>(f: impl FnOnce() -> R) -> R{
+    unsafe{
+        let mut alignment = 0;
+        gl::GetIntegerv(gl::PACK_ALIGNMENT, &mut alignment);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        let out = f();
+        gl::PixelStorei(gl::PACK_ALIGNMENT, alignment);
+        out
+    }
+}
+
+/// Pixels being copied into a pixel pack buffer, readable once the GPU got to the copy.
+///
+/// Poll [`PendingReadback::try_read`] a few frames later to avoid stalling.
+pub struct PendingReadback<T: Pixel>{
+    buffer: Buffer,
+    fence: Fence,
+    len: usize,
+    marker: PhantomData<T>,
+}
+
+impl<T: Pixel> PendingReadback<T>{
+    /// Allocates a pack buffer for the pixels of `region` and calls `transfer` with it bound,
+    /// which issues the copy of `size` bytes to offset 0.
+    pub(crate) fn new(region: &Region, transfer: impl FnOnce(usize)) -> Result<Self, ReadError>{
+        let size = read_size::<T>(region)?;
+        let buffer = Buffer::new(gl::PIXEL_PACK_BUFFER);
+        buffer.allocate(size, gl::STREAM_READ);
+        buffer.bind();
+        let result = check_read(|| with_pack_alignment(|| transfer(size)));
+        buffer.unbind();
+        result?;
+        let fence = Fence::new();
+        unsafe{
+            // make sure the fence reaches the GPU so polling can't wait forever
+            gl::Flush();
+        }
+        Ok(Self{
+            buffer,
+            fence,
+            len: region.len(),
+            marker: PhantomData
+        })
+    }
+
+    /// True once the copy finished and reading won't block.
+    pub fn is_ready(&self) -> bool{
+        self.fence.is_signaled()
+    }
+
+    /// Returns the pixels if the copy finished.
+    pub fn try_read(&self) -> Option<Vec<T>>{
+        self.is_ready().then(|| self.read())
+    }
+
+    /// Blocks until the copy finished or `timeout` passed.
+    pub fn wait(&self, timeout: Duration) -> Option<Vec<T>>{
+        self.fence.client_wait(timeout).is_signaled().then(|| self.read())
+    }
+
+    fn read(&self) -> Vec<T>{
+        let mut out = vec![T::default(); self.len];
+        self.buffer.read_data(0, &mut out);
+        out
+    }
+}
@@ -0,0 +1,511 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, path::PathBuf, time::SystemTime, error::Error, fmt::{self, Display, Formatter}, fs};
+
+use crate::shader::{Shader, ShaderError};
+
+/// Defines injected into a shader, an empty value defines a plain flag.
+pub type Defines = BTreeMap<String, String>;
+
+/// Where the preprocessor reads `#include`d files from.
+///
+/// Paths use `/` as separator and are relative to the root of the file system.
+pub trait FileSystem{
+    fn read(&self, path: &str) -> Option<String>;
+
+    /// Last modification time, used to reload shaders when their files change.
+    fn modified(&self, _path: &str) -> Option<SystemTime>{
+        None
+    }
+}
+
+/// In-memory file system mapping paths to sources.
+impl FileSystem for HashMap<String, String>{
+    fn read(&self, path: &str) -> Option<String> {
+        self.get(path).cloned()
+    }
+}
+
+/// Reads files relative to a directory on disk.
+pub struct DirectoryFs{
+    root: PathBuf
+}
+
+impl DirectoryFs{
+    pub fn new(root: impl Into<PathBuf>) -> Self{
+        Self { root: root.into() }
+    }
+
+    pub fn path(&self, path: &str) -> PathBuf{
+        self.root.join(path)
+    }
+}
+
+impl FileSystem for DirectoryFs{
+    fn read(&self, path: &str) -> Option<String> {
+        fs::read_to_string(self.path(path)).ok()
+    }
+
+    fn modified(&self, path: &str) -> Option<SystemTime> {
+        fs::metadata(self.path(path)).and_then(|x| x.modified()).ok()
+    }
+}
+
+/// Resolves `#include`s and injects `#version` and `#define`s into GLSL sources.
+pub struct Preprocessor{
+    fs: Box<dyn FileSystem>,
+    version: Option<String>,
+    defines: Defines,
+}
+
+impl Preprocessor{
+    pub fn new(fs: impl FileSystem + 'static) -> Self{
+        Self{
+            fs: Box::new(fs),
+            version: None,
+            defines: Defines::new()
+        }
+    }
+
+    /// Overrides the `#version` of every processed shader, e.g. `"450 core"`.
+    pub fn set_version(&mut self, version: &str){
+        self.version = Some(version.to_string());
+    }
+
+    pub fn define(&mut self, name: &str, value: &str){
+        self.defines.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn undefine(&mut self, name: &str){
+        self.defines.remove(name);
+    }
+
+    pub fn modified(&self, path: &str) -> Option<SystemTime>{
+        self.fs.modified(&normalize(path))
+    }
+
+    pub fn process(&self, path: &str) -> Result<PreprocessedSource, PreprocessError>{
+        self.process_with(path, &Defines::new())
+    }
+
+    /// Processes `path` with `defines` added to the preprocessor's own defines.
+    pub fn process_with(&self, path: &str, defines: &Defines) -> Result<PreprocessedSource, PreprocessError>{
+        let mut state = State{
+            out: PreprocessedSource{
+                source: String::new(),
+                files: Vec::new(),
+                line_map: Vec::new()
+            },
+            body: Vec::new(),
+            version: None,
+            stack: Vec::new(),
+            once: HashSet::new()
+        };
+
+        self.expand(&mut state, normalize(path), None)?;
+
+        let mut out = state.out;
+        let version = self.version.clone().or(state.version);
+        if let Some(version) = version{
+            out.push_line(&format!("#version {}", version), None);
+        }
+        for (name, value) in self.defines.iter().chain(defines){
+            out.push_line(&format!("#define {} {}", name, value), None);
+        }
+        for (line, location) in state.body{
+            out.push_line(&line, location);
+        }
+        Ok(out)
+    }
+
+    fn expand(&self, state: &mut State, path: String, from: Option<SourceLocation>) -> Result<(), PreprocessError>{
+        if state.once.contains(&path){
+            return Ok(());
+        }
+        if state.stack.contains(&path){
+            let mut cycle = state.stack.clone();
+            cycle.push(path);
+            return Err(PreprocessError::IncludeCycle(cycle));
+        }
+
+        let src = self.fs.read(&path).ok_or_else(|| PreprocessError::NotFound{
+            path: path.clone(),
+            included_from: from.map(|x| (state.out.files[x.file].clone(), x.line))
+        })?;
+
+        let file = match state.out.files.iter().position(|x| *x == path){
+            Some(file) => file,
+            None => {
+                state.out.files.push(path.clone());
+                state.out.files.len() - 1
+            }
+        };
+        state.stack.push(path.clone());
+
+        for (i, line) in src.lines().enumerate(){
+            let location = SourceLocation{
+                file,
+                line: i as u32 + 1
+            };
+            let directive = line.trim_start();
+
+            if let Some(version) = directive.strip_prefix("#version"){
+                if state.version.is_none(){
+                    state.version = Some(version.trim().to_string());
+                }
+                // keep the line count of the original file
+                state.body.push((String::new(), Some(location)));
+            }
+            else if let Some(include) = directive.strip_prefix("#include"){
+                let include = include.trim();
+                let name = include.strip_prefix('"').and_then(|x| x.strip_suffix('"'))
+                    .or_else(|| include.strip_prefix('<').and_then(|x| x.strip_suffix('>')))
+                    .ok_or_else(|| PreprocessError::Malformed{
+                        file: path.clone(),
+                        line: location.line,
+                        message: format!("Expected a quoted path after #include, found {}", include)
+                    })?;
+                let target = match name.strip_prefix('/'){
+                    Some(absolute) => normalize(absolute),
+                    None => match path.rfind('/'){
+                        Some(dir) => normalize(&format!("{}/{}", &path[..dir], name)),
+                        None => normalize(name)
+                    }
+                };
+                self.expand(state, target, Some(location))?;
+            }
+            else if directive.starts_with("#pragma") && directive["#pragma".len()..].trim() == "once"{
+                state.once.insert(path.clone());
+                state.body.push((String::new(), Some(location)));
+            }
+            else{
+                state.body.push((line.to_string(), Some(location)));
+            }
+        }
+
+        state.stack.pop();
+        Ok(())
+    }
+}
+
+struct State{
+    out: PreprocessedSource,
+    /// Lines after the header, with the location they came from.
+    body: Vec<(String, Option<SourceLocation>)>,
+    /// The first `#version` found, used unless the preprocessor overrides it.
+    version: Option<String>,
+    /// Files currently being expanded, used to detect include cycles.
+    stack: Vec<String>,
+    /// Files marked with `#pragma once`.
+    once: HashSet<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SourceLocation{
+    file: usize,
+    line: u32,
+}
+
+/// Resolves `.` and `..` segments, keeping a leading `/`.
+fn normalize(path: &str) -> String{
+    let mut parts: Vec<&str> = Vec::new();
+    if path.starts_with('/'){
+        parts.push("");
+    }
+    for part in path.split('/'){
+        match part{
+            "" | "." => {},
+            ".." => {
+                if parts.last().is_some_and(|x| !x.is_empty()){
+                    parts.pop();
+                }
+            },
+            part => parts.push(part)
+        }
+    }
+    parts.join("/")
+}
+
+/// A shader source with all includes resolved, and a map back to the files it came from.
+#[derive(Clone, Debug)]
+pub struct PreprocessedSource{
+    source: String,
+    files: Vec<String>,
+    /// Original location of each output line, `None` for injected lines.
+    line_map: Vec<Option<SourceLocation>>,
+}
+
+impl PreprocessedSource{
+    fn push_line(&mut self, line: &str, location: Option<SourceLocation>){
+        self.source.push_str(line);
+        self.source.push('\n');
+        self.line_map.push(location);
+    }
+
+    pub fn source(&self) -> &str{
+        &self.source
+    }
+
+    /// Every file that contributed to the source, the processed file first.
+    pub fn files(&self) -> &[String]{
+        &self.files
+    }
+
+    /// Maps a 1-based line of the processed source to the file and line it came from.
+    pub fn source_location(&self, line: u32) -> Option<(&str, u32)>{
+        let location = (*self.line_map.get((line as usize).checked_sub(1)?)?)?;
+        Some((&self.files[location.file], location.line))
+    }
+
+    /// Rewrites the `0:line` and `0(line)` references of a driver info log to the original files.
+    pub fn map_log(&self, log: &str) -> String{
+        log.lines()
+            .map(|line| self.map_log_line(line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn map_log_line(&self, line: &str) -> String{
+        let bytes = line.as_bytes();
+        for i in 0..bytes.len(){
+            // the source string index, always 0 as the source is passed as a single string
+            if bytes[i] != b'0' || (i > 0 && bytes[i - 1].is_ascii_alphanumeric()){
+                continue;
+            }
+            let (open, close) = match bytes.get(i + 1){
+                Some(b':') => (":", ""),
+                Some(b'(') => ("(", ")"),
+                _ => continue
+            };
+            let digits = line[i + 2..].bytes().take_while(u8::is_ascii_digit).count();
+            if digits == 0{
+                continue;
+            }
+            let end = i + 2 + digits;
+            if !close.is_empty() && bytes.get(end) != Some(&b')'){
+                continue;
+            }
+            let Some((file, original)) = line[i + 2..end].parse().ok().and_then(|x| self.source_location(x)) else{
+                continue;
+            };
+            return format!("{}{}{}{}{}{}", &line[..i], file, open, original, close, &line[end + close.len()..]);
+        }
+        line.to_string()
+    }
+
+    /// Compiles the source, info logs of failed compilations point at the original files.
+    pub fn compile(&self, type_: u32) -> Result<Shader, ShaderError>{
+        let mut shader = Shader::new(type_);
+        shader.try_assign_source(&self.source).map_err(|e| match e{
+            ShaderError::Compile(log) => ShaderError::Compile(self.map_log(&log)),
+            e => e
+        })?;
+        Ok(shader)
+    }
+}
+
+/// Compiles permutations of one shader on demand, keyed by the defines they were built with.
+pub struct ShaderVariants{
+    preprocessor: Preprocessor,
+    type_: u32,
+    path: String,
+    variants: HashMap<Defines, Shader>,
+}
+
+impl ShaderVariants{
+    pub fn new(preprocessor: Preprocessor, type_: u32, path: &str) -> Self{
+        Self{
+            preprocessor,
+            type_,
+            path: path.to_string(),
+            variants: HashMap::new()
+        }
+    }
+
+    /// Returns the variant built with `defines`, compiling it the first time it is requested.
+    pub fn get(&mut self, defines: &Defines) -> Result<Shader, ShaderError>{
+        if let Some(shader) = self.variants.get(defines){
+            return Ok(shader.clone());
+        }
+        let shader = self.preprocessor.process_with(&self.path, defines)?.compile(self.type_)?;
+        self.variants.insert(defines.clone(), shader.clone());
+        Ok(shader)
+    }
+
+    /// Compiles every combination of the `features` flags up front.
+    pub fn compile_permutations(&mut self, features: &[&str]) -> Result<(), ShaderError>{
+        for defines in permutations(features){
+            self.get(&defines)?;
+        }
+        Ok(())
+    }
+}
+
+/// Most features [`permutations`] accepts, already 65536 variants.
+pub const MAX_PERMUTATION_FEATURES: usize = 16;
+
+/// Every combination of the `features` flags being defined or not.
+///
+/// Panics with more than [`MAX_PERMUTATION_FEATURES`] features.
+pub fn permutations(features: &[&str]) -> Vec<Defines>{
+    assert!(
+        features.len() <= MAX_PERMUTATION_FEATURES,
+        "{} features give too many permutations, at most {} are supported",
+        features.len(),
+        MAX_PERMUTATION_FEATURES
+    );
+    (0..1usize << features.len())
+        .map(|mask| {
+            features.iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, name)| (name.to_string(), String::new()))
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub enum PreprocessError{
+    NotFound{
+        path: String,
+        /// File and line of the `#include`, `None` for the processed file itself.
+        included_from: Option<(String, u32)>
+    },
+    /// The chain of includes that leads back to a file being expanded.
+    IncludeCycle(Vec<String>),
+    Malformed{
+        file: String,
+        line: u32,
+        message: String
+    },
+}
+
+impl Display for PreprocessError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self{
+            PreprocessError::NotFound { path, included_from: Some((file, line)) } => write!(f, "{}:{}: Can't find included file {}", file, line, path),
+            PreprocessError::NotFound { path, included_from: None } => write!(f, "Can't find shader file {}", path),
+            PreprocessError::IncludeCycle(files) => write!(f, "Include cycle: {}", files.join(" -> ")),
+            PreprocessError::Malformed { file, line, message } => write!(f, "{}:{}: {}", file, line, message),
+        }
+    }
+}
+
+impl Error for PreprocessError{}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn preprocessor(files: &[(&str, &str)]) -> Preprocessor{
+        let fs: HashMap<String, String> = files.iter()
+            .map(|(path, src)| (path.to_string(), src.to_string()))
+            .collect();
+        Preprocessor::new(fs)
+    }
+
+    #[test]
+    fn resolves_includes(){
+        let pre = preprocessor(&[
+            ("shaders/main.glsl", "#version 450\n#include \"lib/light.glsl\"\nvoid main(){}"),
+            ("shaders/lib/light.glsl", "#include \"../common.glsl\"\nvec3 light;"),
+            ("shaders/common.glsl", "#include </util.glsl>\nfloat common;"),
+            ("util.glsl", "float util;"),
+        ]);
+        let out = pre.process("shaders/main.glsl").unwrap();
+        let lines: Vec<_> = out.source().lines().collect();
+        assert_eq!(lines, ["#version 450", "", "float util;", "float common;", "vec3 light;", "void main(){}"]);
+        assert_eq!(out.files(), ["shaders/main.glsl", "shaders/lib/light.glsl", "shaders/common.glsl", "util.glsl"]);
+    }
+
+    #[test]
+    fn reports_missing_includes(){
+        let pre = preprocessor(&[("main.glsl", "\n#include \"missing.glsl\"")]);
+        match pre.process("main.glsl"){
+            Err(PreprocessError::NotFound { path, included_from }) => {
+                assert_eq!(path, "missing.glsl");
+                assert_eq!(included_from, Some(("main.glsl".to_string(), 2)));
+            },
+            x => panic!("unexpected result {:?}", x)
+        }
+        assert!(matches!(pre.process("other.glsl"), Err(PreprocessError::NotFound { included_from: None, .. })));
+    }
+
+    #[test]
+    fn detects_include_cycles(){
+        let pre = preprocessor(&[
+            ("a.glsl", "#include \"b.glsl\""),
+            ("b.glsl", "#include \"c.glsl\""),
+            ("c.glsl", "#include \"a.glsl\""),
+        ]);
+        match pre.process("a.glsl"){
+            Err(PreprocessError::IncludeCycle(files)) => assert_eq!(files, ["a.glsl", "b.glsl", "c.glsl", "a.glsl"]),
+            x => panic!("unexpected result {:?}", x)
+        }
+    }
+
+    #[test]
+    fn pragma_once_includes_a_file_once(){
+        let pre = preprocessor(&[
+            ("main.glsl", "#include \"a.glsl\"\n#include \"b.glsl\"\n#include \"a.glsl\""),
+            ("a.glsl", "#pragma once\nfloat a;"),
+            ("b.glsl", "#include \"a.glsl\"\nfloat b;"),
+        ]);
+        let out = pre.process("main.glsl").unwrap();
+        assert_eq!(out.source().matches("float a;").count(), 1);
+        assert!(out.source().contains("float b;"));
+    }
+
+    #[test]
+    fn injects_version_and_defines(){
+        let mut pre = preprocessor(&[("main.glsl", "#version 330\nvoid main(){}")]);
+        pre.set_version("450 core");
+        pre.define("A", "1");
+        let defines = permutations(&["B"]).pop().unwrap();
+        let out = pre.process_with("main.glsl", &defines).unwrap();
+        let lines: Vec<_> = out.source().lines().collect();
+        assert_eq!(lines, ["#version 450 core", "#define A 1", "#define B ", "", "void main(){}"]);
+    }
+
+    #[test]
+    fn maps_lines_to_their_files(){
+        let pre = preprocessor(&[
+            ("main.glsl", "#version 450\n#include \"lib.glsl\"\nvoid main(){}"),
+            ("lib.glsl", "float a;\nfloat b;"),
+        ]);
+        let out = pre.process("main.glsl").unwrap();
+        // version, then the blanked out version line, lib.glsl and main's body
+        assert_eq!(out.source_location(1), None);
+        assert_eq!(out.source_location(2), Some(("main.glsl", 1)));
+        assert_eq!(out.source_location(3), Some(("lib.glsl", 1)));
+        assert_eq!(out.source_location(4), Some(("lib.glsl", 2)));
+        assert_eq!(out.source_location(5), Some(("main.glsl", 3)));
+        assert_eq!(out.source_location(0), None);
+        assert_eq!(out.source_location(6), None);
+
+        let log = "ERROR: 0:4: 'b' : redefinition\n0(5) : error C0000: syntax error\nERROR: 1 compilation errors.";
+        assert_eq!(
+            out.map_log(log),
+            "ERROR: lib.glsl:2: 'b' : redefinition\nmain.glsl(3) : error C0000: syntax error\nERROR: 1 compilation errors."
+        );
+        // lines without a known location are left alone
+        assert_eq!(out.map_log("ERROR: 0:99: error"), "ERROR: 0:99: error");
+    }
+
+    #[test]
+    fn permutations_cover_every_combination(){
+        let all = permutations(&["A", "B", "C"]);
+        assert_eq!(all.len(), 8);
+        let unique: HashSet<_> = all.iter().collect();
+        assert_eq!(unique.len(), 8);
+        assert!(all.iter().any(Defines::is_empty));
+        assert!(all.iter().any(|x| x.len() == 3));
+        assert_eq!(permutations(&[]), [Defines::new()]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn permutations_reject_too_many_features(){
+        let features = ["F"; MAX_PERMUTATION_FEATURES + 1];
+        permutations(&features);
+    }
+}
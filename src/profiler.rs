@@ -0,0 +1,368 @@
+use std::{collections::{HashMap, VecDeque}, io::{self, Write}, ops::Deref, time::Duration};
+
+use crate::query::{Query, QueryType};
+
+/// Timings of one scope over every frame measured since the last reset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScopeStats{
+    pub count: u32,
+    pub min: Duration,
+    pub max: Duration,
+    pub total: Duration,
+}
+
+impl ScopeStats{
+    fn new(time: Duration) -> Self{
+        Self{
+            count: 1,
+            min: time,
+            max: time,
+            total: time
+        }
+    }
+
+    fn add(&mut self, time: Duration){
+        self.count += 1;
+        self.min = self.min.min(time);
+        self.max = self.max.max(time);
+        self.total += time;
+    }
+
+    pub fn average(&self) -> Duration{
+        self.total / self.count.max(1)
+    }
+}
+
+/// A measured scope, as exported to the trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScopeTiming{
+    pub name: String,
+    pub frame: u64,
+    /// Nesting depth, 0 for scopes opened directly in the frame.
+    pub depth: usize,
+    /// GPU timestamp in nanoseconds.
+    pub start: u64,
+    pub duration: Duration,
+}
+
+struct PendingScope{
+    /// Names of the enclosing scopes and this one joined with `/`.
+    path: String,
+    depth: usize,
+    start: Query,
+    end: Option<Query>,
+}
+
+struct Frame{
+    index: u64,
+    scopes: Vec<PendingScope>,
+}
+
+impl Frame{
+    fn is_available(&self) -> bool{
+        self.scopes.iter().all(|x| x.end.as_ref().is_some_and(Query::is_available))
+    }
+}
+
+/// Measures named, nested scopes of GPU work with timestamp queries.
+///
+/// Results are read `latency` frames after they were recorded so the CPU doesn't wait on
+/// the GPU, and the queries are reused afterwards.
+///
+/// ```ignore
+/// profiler.begin_frame();
+/// {
+///     let mut frame = profiler.scope("scene");
+///     let _shadows = frame.scope("shadows");
+///     // draw shadows
+/// }
+/// profiler.end_frame();
+/// ```
+pub struct GpuProfiler{
+    latency: usize,
+    free: Vec<Query>,
+    frames: VecDeque<Frame>,
+    current: Option<Frame>,
+    /// Indices of the open scopes in the current frame.
+    stack: Vec<usize>,
+    next_frame: u64,
+    stats: HashMap<String, ScopeStats>,
+    trace: VecDeque<ScopeTiming>,
+    trace_capacity: usize,
+}
+
+impl GpuProfiler{
+    /// A profiler reading results `latency` frames later, 2 or 3 keeps it from stalling.
+    pub fn new(latency: usize) -> Self{
+        Self{
+            latency,
+            free: Vec::new(),
+            frames: VecDeque::new(),
+            current: None,
+            stack: Vec::new(),
+            next_frame: 0,
+            stats: HashMap::new(),
+            trace: VecDeque::new(),
+            trace_capacity: 65536
+        }
+    }
+
+    /// Collects the results of earlier frames and starts recording a new one.
+    pub fn begin_frame(&mut self){
+        if self.current.is_some(){
+            panic!("begin_frame called twice without end_frame");
+        }
+        self.collect();
+        self.current = Some(Frame{
+            index: self.next_frame,
+            scopes: Vec::new()
+        });
+        self.next_frame += 1;
+    }
+
+    pub fn end_frame(&mut self){
+        if !self.stack.is_empty(){
+            panic!("end_frame called with {} scopes still open", self.stack.len());
+        }
+        let frame = self.current.take().expect("end_frame called without begin_frame");
+        self.frames.push_back(frame);
+    }
+
+    /// Measures the GPU work until the returned scope is dropped.
+    ///
+    /// Nested scopes are opened with [`ProfilerScope::scope`].
+    pub fn scope(&mut self, name: &str) -> ProfilerScope<'_>{
+        self.begin_scope(name);
+        ProfilerScope{
+            profiler: self
+        }
+    }
+
+    /// Opens a scope that lasts until [`GpuProfiler::end_scope`], for scopes that don't fit a block.
+    pub fn begin_scope(&mut self, name: &str){
+        if self.current.is_none(){
+            panic!("Profiler scopes need begin_frame first");
+        }
+        let start = self.query();
+        start.record_timestamp();
+
+        let frame = self.current.as_mut().unwrap();
+        let path = match self.stack.last(){
+            Some(&parent) => format!("{}/{}", frame.scopes[parent].path, name),
+            None => name.to_string()
+        };
+        self.stack.push(frame.scopes.len());
+        frame.scopes.push(PendingScope{
+            path,
+            depth: self.stack.len() - 1,
+            start,
+            end: None
+        });
+    }
+
+    pub fn end_scope(&mut self){
+        let index = self.stack.pop().expect("end_scope called without an open scope");
+        let end = self.query();
+        end.record_timestamp();
+        self.current.as_mut().unwrap().scopes[index].end = Some(end);
+    }
+
+    fn query(&mut self) -> Query{
+        self.free.pop().unwrap_or_else(|| Query::new(QueryType::Timestamp))
+    }
+
+    /// Reads every finished frame, and waits for the ones older than the latency.
+    fn collect(&mut self){
+        while let Some(frame) = self.frames.front(){
+            if self.frames.len() <= self.latency && !frame.is_available(){
+                break;
+            }
+            let frame = self.frames.pop_front().unwrap();
+            self.resolve(frame);
+        }
+    }
+
+    fn resolve(&mut self, frame: Frame){
+        for scope in frame.scopes{
+            let end = scope.end.unwrap();
+            let start_time = scope.start.result();
+            let duration = Duration::from_nanos(end.result().saturating_sub(start_time));
+
+            match self.stats.get_mut(&scope.path){
+                Some(stats) => stats.add(duration),
+                None => {
+                    self.stats.insert(scope.path.clone(), ScopeStats::new(duration));
+                }
+            }
+
+            if self.trace_capacity > 0{
+                if self.trace.len() == self.trace_capacity{
+                    self.trace.pop_front();
+                }
+                let name = scope.path.rsplit('/').next().unwrap().to_string();
+                self.trace.push_back(ScopeTiming{
+                    name,
+                    frame: frame.index,
+                    depth: scope.depth,
+                    start: start_time,
+                    duration
+                });
+            }
+
+            self.free.push(scope.start);
+            self.free.push(end);
+        }
+    }
+
+    /// Statistics per scope, keyed by the scope names from the outermost scope joined with `/`.
+    pub fn stats(&self) -> &HashMap<String, ScopeStats>{
+        &self.stats
+    }
+
+    pub fn reset_stats(&mut self){
+        self.stats.clear();
+    }
+
+    /// The most recent measured scopes, oldest first.
+    pub fn timings(&self) -> impl Iterator<Item = &ScopeTiming>{
+        self.trace.iter()
+    }
+
+    /// How many scopes are kept for [`GpuProfiler::timings`] and the trace export, 0 keeps none.
+    pub fn set_trace_capacity(&mut self, capacity: usize){
+        self.trace_capacity = capacity;
+        while self.trace.len() > capacity{
+            self.trace.pop_front();
+        }
+    }
+
+    /// Writes the kept timings in the Chrome trace event format, for `chrome://tracing` or Perfetto.
+    pub fn write_chrome_trace(&self, out: &mut impl Write) -> io::Result<()>{
+        let origin = self.trace.iter().map(|x| x.start).min().unwrap_or(0);
+        writeln!(out, "{{\"traceEvents\":[")?;
+        for (i, timing) in self.trace.iter().enumerate(){
+            write!(out, "{{\"name\":\"")?;
+            write_json_str(out, &timing.name)?;
+            write!(
+                out,
+                "\",\"cat\":\"gpu\",\"ph\":\"X\",\"pid\":0,\"tid\":0,\"ts\":{:.3},\"dur\":{:.3},\"args\":{{\"frame\":{}}}}}",
+                (timing.start - origin) as f64 / 1000.0,
+                timing.duration.as_nanos() as f64 / 1000.0,
+                timing.frame
+            )?;
+            writeln!(out, "{}", if i + 1 < self.trace.len() { "," } else { "" })?;
+        }
+        writeln!(out, "],\"displayTimeUnit\":\"ns\"}}")
+    }
+}
+
+fn write_json_str(out: &mut impl Write, x: &str) -> io::Result<()>{
+    for c in x.chars(){
+        match c{
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    Ok(())
+}
+
+/// An open profiler scope, ends when dropped.
+pub struct ProfilerScope<'a>{
+    profiler: &'a mut GpuProfiler
+}
+
+impl ProfilerScope<'_>{
+    /// Opens a scope nested in this one, this one can't be used until it's dropped.
+    pub fn scope(&mut self, name: &str) -> ProfilerScope<'_>{
+        self.profiler.scope(name)
+    }
+}
+
+impl Deref for ProfilerScope<'_>{
+    type Target = GpuProfiler;
+
+    fn deref(&self) -> &GpuProfiler {
+        self.profiler
+    }
+}
+
+impl Drop for ProfilerScope<'_>{
+    fn drop(&mut self) {
+        self.profiler.end_scope();
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn timing(name: &str, frame: u64, start: u64, duration: u64) -> ScopeTiming{
+        ScopeTiming{
+            name: name.to_string(),
+            frame,
+            depth: 0,
+            start,
+            duration: Duration::from_nanos(duration)
+        }
+    }
+
+    #[test]
+    fn scope_stats(){
+        let mut stats = ScopeStats::new(Duration::from_micros(30));
+        stats.add(Duration::from_micros(10));
+        stats.add(Duration::from_micros(20));
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.min, Duration::from_micros(10));
+        assert_eq!(stats.max, Duration::from_micros(30));
+        assert_eq!(stats.total, Duration::from_micros(60));
+        assert_eq!(stats.average(), Duration::from_micros(20));
+    }
+
+    #[test]
+    fn json_strings_are_escaped(){
+        let mut out = Vec::new();
+        write_json_str(&mut out, "a \"b\" \\ c\n\u{1}é").unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "a \\\"b\\\" \\\\ c\\u000a\\u0001é");
+    }
+
+    #[test]
+    fn chrome_trace(){
+        let mut profiler = GpuProfiler::new(2);
+        let mut out = Vec::new();
+        profiler.write_chrome_trace(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "{\"traceEvents\":[\n],\"displayTimeUnit\":\"ns\"}\n");
+
+        profiler.trace.push_back(timing("scene", 0, 5000, 2500));
+        profiler.trace.push_back(timing("sha\"dows", 1, 6000, 1000));
+        let mut out = Vec::new();
+        profiler.write_chrome_trace(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "{\"traceEvents\":[\n",
+                "{\"name\":\"scene\",\"cat\":\"gpu\",\"ph\":\"X\",\"pid\":0,\"tid\":0,\"ts\":0.000,\"dur\":2.500,\"args\":{\"frame\":0}},\n",
+                "{\"name\":\"sha\\\"dows\",\"cat\":\"gpu\",\"ph\":\"X\",\"pid\":0,\"tid\":0,\"ts\":1.000,\"dur\":1.000,\"args\":{\"frame\":1}}\n",
+                "],\"displayTimeUnit\":\"ns\"}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn trace_capacity_drops_the_oldest(){
+        let mut profiler = GpuProfiler::new(2);
+        for i in 0..4{
+            profiler.trace.push_back(timing("x", i, i * 10, 1));
+        }
+        profiler.set_trace_capacity(2);
+        let frames: Vec<_> = profiler.timings().map(|x| x.frame).collect();
+        assert_eq!(frames, [2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "begin_frame")]
+    fn scopes_need_a_frame(){
+        GpuProfiler::new(2).begin_scope("scene");
+    }
+}
@@ -0,0 +1,113 @@
+use std::{ffi::CStr, fs, hash::Hasher, path::PathBuf};
+
+use crate::shader::{Program, Shader, ShaderError};
+
+const MAGIC: &[u8; 4] = b"GLWB";
+
+/// Caches linked program binaries on disk to skip compiling and linking on later runs.
+///
+/// Entries are keyed by the shader sources and the driver, so a driver update
+/// invalidates the cache. Binaries the driver rejects anyway are rebuilt.
+pub struct ProgramCache{
+    dir: PathBuf
+}
+
+impl ProgramCache{
+    pub fn new(dir: impl Into<PathBuf>) -> Self{
+        Self { dir: dir.into() }
+    }
+
+    /// Loads the program for `sources`, a list of shader types and their sources, from
+    /// the cache, or builds it and stores the binary.
+    ///
+    /// The sources are compiled as they are, so run them through the
+    /// [`Preprocessor`](crate::preprocessor::Preprocessor) first to apply includes and defines.
+    /// Failing to write the cache isn't an error, the program is built either way.
+    pub fn get_or_build(&self, sources: &[(u32, &str)]) -> Result<Program, ShaderError>{
+        let path = self.dir.join(format!("{:016x}.bin", Self::key(sources)));
+
+        if let Some((format, binary)) = fs::read(&path).ok().as_deref().and_then(Self::decode){
+            if let Ok(program) = Program::from_binary(format, binary){
+                return Ok(program);
+            }
+        }
+
+        let mut builder = Program::builder().binary_retrievable(true);
+        for (type_, source) in sources{
+            let mut shader = Shader::new(*type_);
+            shader.try_assign_source(source)?;
+            builder = builder.shader(shader);
+        }
+        let program = builder.build()?;
+
+        if let Some((format, binary)) = program.get_binary(){
+            let mut data = Vec::with_capacity(MAGIC.len() + 4 + binary.len());
+            data.extend_from_slice(MAGIC);
+            data.extend_from_slice(&format.to_le_bytes());
+            data.extend_from_slice(&binary);
+            let _ = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, data));
+        }
+
+        Ok(program)
+    }
+
+    /// Hash of everything that influences the binary, needs a current context for the driver strings.
+    pub fn key(sources: &[(u32, &str)]) -> u64{
+        let mut hasher = Fnv64::default();
+        for name in [gl::VENDOR, gl::RENDERER, gl::VERSION]{
+            hasher.write_str(&driver_string(name));
+        }
+        for (type_, source) in sources{
+            hasher.write(&type_.to_le_bytes());
+            hasher.write_str(source);
+        }
+        hasher.finish()
+    }
+
+    fn decode(data: &[u8]) -> Option<(u32, &[u8])>{
+        let data = data.strip_prefix(MAGIC)?;
+        let format = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?);
+        Some((format, &data[4..]))
+    }
+}
+
+fn driver_string(name: u32) -> String{
+    unsafe{
+        let ptr = gl::GetString(name);
+        if ptr.is_null(){
+            return String::new();
+        }
+        CStr::from_ptr(ptr.cast()).to_string_lossy().to_string()
+    }
+}
+
+/// FNV-1a over bytes written explicitly in a fixed layout, so keys don't change between
+/// Rust versions like the output of `Hash` implementations can.
+struct Fnv64(u64);
+
+impl Fnv64{
+    /// Writes the length first so neighbouring strings can't run into each other.
+    fn write_str(&mut self, x: &str){
+        self.write(&(x.len() as u64).to_le_bytes());
+        self.write(x.as_bytes());
+    }
+}
+
+impl Default for Fnv64{
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv64{
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes{
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
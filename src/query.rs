@@ -0,0 +1,155 @@
+use std::rc::Rc;
+
+use crate::internal::{RawId, RawIdManager};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QueryType{
+    /// Nanoseconds the GPU spent on the commands of a scope.
+    TimeElapsed = gl::TIME_ELAPSED as _,
+    /// GPU time in nanoseconds when the commands before it finished, see [`Query::record_timestamp`].
+    Timestamp = gl::TIMESTAMP as _,
+    SamplesPassed = gl::SAMPLES_PASSED as _,
+    /// 1 if any sample passed the depth and stencil tests, 0 otherwise.
+    AnySamplesPassed = gl::ANY_SAMPLES_PASSED as _,
+    /// Like `AnySamplesPassed`, but may report 1 when no samples passed.
+    AnySamplesPassedConservative = gl::ANY_SAMPLES_PASSED_CONSERVATIVE as _,
+    PrimitivesGenerated = gl::PRIMITIVES_GENERATED as _,
+}
+
+impl QueryType{
+    /// True for the occlusion queries [`VertexArray::draw_conditional`](crate::vao::VertexArray::draw_conditional) accepts.
+    pub fn is_occlusion(self) -> bool{
+        matches!(self, Self::SamplesPassed | Self::AnySamplesPassed | Self::AnySamplesPassedConservative)
+    }
+}
+
+/// How conditional rendering waits for the result of its query.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ConditionalMode{
+    /// Waits for the result.
+    Wait = gl::QUERY_WAIT as _,
+    /// Draws anyway if the result isn't available yet.
+    NoWait = gl::QUERY_NO_WAIT as _,
+    ByRegionWait = gl::QUERY_BY_REGION_WAIT as _,
+    ByRegionNoWait = gl::QUERY_BY_REGION_NO_WAIT as _,
+}
+
+/// Measures something about the commands the GPU executes, read the result once it's
+/// available to avoid stalling.
+#[derive(Clone)]
+pub struct Query{
+    type_: QueryType,
+    id: Rc<RawId<Self>>
+}
+
+impl Query{
+    pub fn new(type_: QueryType) -> Self{
+        unsafe{
+            let mut id = 0;
+            gl::CreateQueries(type_ as u32, 1, &mut id);
+            Self{
+                type_,
+                id: Rc::new(RawId::from_id(id))
+            }
+        }
+    }
+
+    /// Starts measuring, until the returned scope is dropped.
+    ///
+    /// Only one query of a type can be active at a time. Panics for `Timestamp` queries.
+    pub fn begin(&self) -> QueryScope<'_>{
+        if self.type_ == QueryType::Timestamp{
+            panic!("Timestamp queries can't be scoped, use record_timestamp");
+        }
+        unsafe{
+            gl::BeginQuery(self.type_ as u32, self.id());
+        }
+        QueryScope{
+            query: self
+        }
+    }
+
+    /// Records the GPU time once every command before it finished.
+    ///
+    /// Panics for queries that aren't `Timestamp` queries.
+    pub fn record_timestamp(&self){
+        if self.type_ != QueryType::Timestamp{
+            panic!("record_timestamp needs a Timestamp query, got {:?}", self.type_);
+        }
+        unsafe{
+            gl::QueryCounter(self.id(), gl::TIMESTAMP);
+        }
+    }
+
+    /// True once the result can be read without blocking.
+    ///
+    /// The query has to have been started or recorded before.
+    pub fn is_available(&self) -> bool{
+        unsafe{
+            let mut available = 0;
+            gl::GetQueryObjectiv(self.id(), gl::QUERY_RESULT_AVAILABLE, &mut available);
+            available != gl::FALSE as _
+        }
+    }
+
+    /// Returns the result if it's available.
+    pub fn try_result(&self) -> Option<u64>{
+        self.is_available().then(|| self.result())
+    }
+
+    /// Waits for the GPU and returns the result.
+    pub fn result(&self) -> u64{
+        unsafe{
+            let mut result = 0;
+            gl::GetQueryObjectui64v(self.id(), gl::QUERY_RESULT, &mut result);
+            result
+        }
+    }
+
+    pub fn query_type(&self) -> QueryType{
+        self.type_
+    }
+
+    pub(crate) fn id(&self) -> u32{
+        self.id.id()
+    }
+}
+
+impl RawIdManager for Query{
+    fn create_resource() -> u32 {
+        panic!("Needs to be created manually");
+    }
+
+    fn delete_resource(id: u32) {
+        unsafe{
+            gl::DeleteQueries(1, &id);
+        }
+    }
+}
+
+/// Ends the query when dropped.
+pub struct QueryScope<'a>{
+    query: &'a Query
+}
+
+impl QueryScope<'_>{
+    /// Ends the query now instead of at the end of the scope.
+    pub fn end(self){}
+}
+
+impl Drop for QueryScope<'_>{
+    fn drop(&mut self) {
+        unsafe{
+            gl::EndQuery(self.query.type_ as u32);
+        }
+    }
+}
+
+/// The current GPU time in nanoseconds, without waiting for queued commands.
+pub fn gpu_timestamp() -> u64{
+    unsafe{
+        let mut time = 0;
+        gl::GetInteger64v(gl::TIMESTAMP, &mut time);
+        time as u64
+    }
+}
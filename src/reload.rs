@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{preprocessor::{DirectoryFs, Preprocessor}, shader::{Program, ProgramBuilder, Shader, ShaderError}};
+
+/// A shader stage and the modification times of every file it was built from.
+struct WatchedShader{
+    type_: u32,
+    path: String,
+    files: Vec<(String, Option<SystemTime>)>,
+}
+
+impl WatchedShader{
+    fn changed(&self, preprocessor: &Preprocessor) -> bool{
+        self.files.iter().any(|(path, modified)| preprocessor.modified(path) != *modified)
+    }
+}
+
+/// A [`Program`] that is rebuilt when one of its source files changes on disk.
+///
+/// If the new sources fail to compile or link the previous program stays in use
+/// and the error is reported by [`ReloadableProgram::poll`].
+pub struct ReloadableProgram{
+    preprocessor: Preprocessor,
+    shaders: Vec<WatchedShader>,
+    program: Program,
+    last_error: Option<ShaderError>,
+    poll_interval: Duration,
+    last_poll: Instant,
+}
+
+impl ReloadableProgram{
+    /// Builds the program from files on disk, paths are relative to the working directory.
+    ///
+    /// `shaders` pairs each stage's type, like `gl::VERTEX_SHADER`, with its path.
+    pub fn from_files(shaders: &[(u32, &str)]) -> Result<Self, ShaderError>{
+        Self::new(Preprocessor::new(DirectoryFs::new("")), shaders)
+    }
+
+    /// Builds the program from paths in the file system of `preprocessor`.
+    ///
+    /// Included files are watched too, as long as the file system reports modification times.
+    pub fn new(preprocessor: Preprocessor, shaders: &[(u32, &str)]) -> Result<Self, ShaderError>{
+        let mut shaders: Vec<_> = shaders.iter()
+            .map(|&(type_, path)| WatchedShader{
+                type_,
+                path: path.to_string(),
+                files: Vec::new()
+            })
+            .collect();
+        let program = Self::build(&preprocessor, &mut shaders)?;
+
+        Ok(Self{
+            preprocessor,
+            shaders,
+            program,
+            last_error: None,
+            poll_interval: Duration::from_millis(250),
+            last_poll: Instant::now()
+        })
+    }
+
+    /// Rebuilds the program if a source file changed since the last poll.
+    ///
+    /// Returns `Ok(true)` if the program was replaced, and the error if the changed
+    /// sources don't build. Files are checked at most once per poll interval.
+    pub fn poll(&mut self) -> Result<bool, ShaderError>{
+        if self.last_poll.elapsed() < self.poll_interval{
+            return Ok(false);
+        }
+        self.last_poll = Instant::now();
+
+        if !self.shaders.iter().any(|x| x.changed(&self.preprocessor)){
+            return Ok(false);
+        }
+
+        match Self::build(&self.preprocessor, &mut self.shaders){
+            Ok(program) => {
+                self.program = program;
+                self.last_error = None;
+                Ok(true)
+            },
+            Err(e) => {
+                self.last_error = Some(e.clone());
+                Err(e)
+            }
+        }
+    }
+
+    /// The last successfully built program.
+    pub fn program(&self) -> &Program{
+        &self.program
+    }
+
+    /// The error of the last rebuild, `None` once the sources build again.
+    pub fn last_error(&self) -> Option<&ShaderError>{
+        self.last_error.as_ref()
+    }
+
+    pub fn set_poll_interval(&mut self, interval: Duration){
+        self.poll_interval = interval;
+    }
+
+    fn build(preprocessor: &Preprocessor, shaders: &mut [WatchedShader]) -> Result<Program, ShaderError>{
+        // compile every stage before returning an error, so each records the files it used
+        let compiled: Vec<_> = shaders.iter_mut()
+            .map(|shader| Self::compile(preprocessor, shader))
+            .collect();
+        compiled.into_iter()
+            .try_fold(Program::builder(), |builder, shader| Ok(builder.shader(shader?)))
+            .and_then(ProgramBuilder::build)
+    }
+
+    /// Compiles a stage and records the modification times of its files, even if it fails,
+    /// so a broken file isn't rebuilt until it changes again.
+    fn compile(preprocessor: &Preprocessor, shader: &mut WatchedShader) -> Result<Shader, ShaderError>{
+        let source = preprocessor.process(&shader.path);
+
+        let mut files: Vec<String> = match &source{
+            Ok(source) => source.files().to_vec(),
+            // keep watching what the last build used, the broken include may be among them
+            Err(_) => shader.files.iter().map(|(path, _)| path.clone()).collect()
+        };
+        if files.is_empty(){
+            files.push(shader.path.clone());
+        }
+        shader.files = files.into_iter()
+            .map(|path| {
+                let modified = preprocessor.modified(&path);
+                (path, modified)
+            })
+            .collect();
+
+        source?.compile(shader.type_)
+    }
+}
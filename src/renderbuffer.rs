@@ -0,0 +1,79 @@
+use std::rc::Rc;
+
+use crate::{internal::{RawId, RawIdManager}, texture::{self, UnsupportedSamples}};
+
+/// Storage for a framebuffer attachment that's rendered to but never sampled.
+#[derive(Clone)]
+pub struct Renderbuffer{
+    internal_format: u32,
+    width: i32,
+    height: i32,
+    /// 0 if the renderbuffer isn't multisampled.
+    samples: i32,
+    id: Rc<RawId<Self>>
+}
+
+impl Renderbuffer{
+    pub fn new(internal_format: u32, width: i32, height: i32) -> Self{
+        let out = Self::create(internal_format, width, height, 0);
+        unsafe{
+            gl::NamedRenderbufferStorage(out.id(), internal_format, width, height);
+        }
+        out
+    }
+
+    pub fn new_multisample(internal_format: u32, width: i32, height: i32, samples: i32) -> Result<Self, UnsupportedSamples>{
+        texture::check_samples(gl::RENDERBUFFER, internal_format, samples)?;
+        let out = Self::create(internal_format, width, height, samples);
+        unsafe{
+            gl::NamedRenderbufferStorageMultisample(out.id(), samples, internal_format, width, height);
+        }
+        Ok(out)
+    }
+
+    fn create(internal_format: u32, width: i32, height: i32, samples: i32) -> Self{
+        Self{
+            internal_format,
+            width,
+            height,
+            samples,
+            id: Rc::new(RawId::new())
+        }
+    }
+
+    pub fn internal_format(&self) -> u32{
+        self.internal_format
+    }
+
+    pub fn width(&self) -> i32{
+        self.width
+    }
+
+    pub fn height(&self) -> i32{
+        self.height
+    }
+
+    pub fn samples(&self) -> i32{
+        self.samples
+    }
+
+    pub(crate) fn id(&self) -> u32{
+        self.id.id()
+    }
+}
+
+impl RawIdManager for Renderbuffer{
+    fn create_resource() -> u32 {
+        unsafe{
+            let mut i = 0;
+            gl::CreateRenderbuffers(1, &mut i);
+            i
+        }
+    }
+
+    fn delete_resource(id: u32) {
+        unsafe{
+            gl::DeleteRenderbuffers(1, &id);
+        }
+    }
+}
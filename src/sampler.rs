@@ -0,0 +1,210 @@
+use std::rc::Rc;
+
+use crate::{ext, internal::{RawId, RawIdManager}};
+
+/// Filtering and wrapping state that's bound to a texture unit next to a texture,
+/// overriding the texture's own parameters.
+#[derive(Clone)]
+pub struct Sampler{
+    id: Rc<RawId<Self>>
+}
+
+impl Sampler{
+    /// A sampler with the GL defaults.
+    pub fn new() -> Self{
+        Self{
+            id: Rc::new(RawId::new())
+        }
+    }
+
+    pub fn builder() -> SamplerBuilder{
+        SamplerBuilder::new()
+    }
+
+    pub fn set_parameter(&self, pname: u32, value: i32){
+        unsafe{
+            gl::SamplerParameteri(self.id(), pname, value);
+        }
+    }
+
+    pub fn set_parameter_f(&self, pname: u32, value: f32){
+        unsafe{
+            gl::SamplerParameterf(self.id(), pname, value);
+        }
+    }
+
+    /// Uses this sampler for whatever texture is bound to `unit`.
+    pub fn bind(&self, unit: u32){
+        unsafe{
+            gl::BindSampler(unit, self.id());
+        }
+    }
+
+    /// Makes `unit` sample with the parameters of its texture again.
+    pub fn unbind(unit: u32){
+        unsafe{
+            gl::BindSampler(unit, 0);
+        }
+    }
+
+    pub(crate) fn id(&self) -> u32{
+        self.id.id()
+    }
+}
+
+impl Default for Sampler{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RawIdManager for Sampler{
+    fn create_resource() -> u32 {
+        unsafe{
+            let mut i = 0;
+            gl::CreateSamplers(1, &mut i);
+            i
+        }
+    }
+
+    fn delete_resource(id: u32) {
+        unsafe{
+            gl::DeleteSamplers(1, &id);
+        }
+    }
+}
+
+/// Builds a [`Sampler`], every setting starts at its GL default.
+#[derive(Clone, Debug)]
+pub struct SamplerBuilder{
+    min_filter: u32,
+    mag_filter: u32,
+    wrap: [u32; 3],
+    max_anisotropy: f32,
+    lod_bias: f32,
+    min_lod: f32,
+    max_lod: f32,
+    border_color: [f32; 4],
+    compare_func: Option<u32>,
+}
+
+impl SamplerBuilder{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    /// `gl::NEAREST`, `gl::LINEAR` or one of the mipmap variants like `gl::LINEAR_MIPMAP_LINEAR`.
+    pub fn min_filter(mut self, filter: u32) -> Self{
+        self.min_filter = filter;
+        self
+    }
+
+    /// `gl::NEAREST` or `gl::LINEAR`.
+    pub fn mag_filter(mut self, filter: u32) -> Self{
+        self.mag_filter = filter;
+        self
+    }
+
+    /// Sets both filters, mipmapped linear filtering for `gl::LINEAR`.
+    pub fn filter(self, filter: u32) -> Self{
+        let min_filter = if filter == gl::LINEAR { gl::LINEAR_MIPMAP_LINEAR } else { gl::NEAREST_MIPMAP_NEAREST };
+        self.min_filter(min_filter).mag_filter(filter)
+    }
+
+    /// Sets the wrap mode of every coordinate, `gl::REPEAT`, `gl::CLAMP_TO_EDGE`, ...
+    pub fn wrap(mut self, wrap: u32) -> Self{
+        self.wrap = [wrap; 3];
+        self
+    }
+
+    pub fn wrap_s(mut self, wrap: u32) -> Self{
+        self.wrap[0] = wrap;
+        self
+    }
+
+    pub fn wrap_t(mut self, wrap: u32) -> Self{
+        self.wrap[1] = wrap;
+        self
+    }
+
+    pub fn wrap_r(mut self, wrap: u32) -> Self{
+        self.wrap[2] = wrap;
+        self
+    }
+
+    /// Clamped to what the driver supports, ignored without anisotropic filtering support.
+    pub fn max_anisotropy(mut self, anisotropy: f32) -> Self{
+        self.max_anisotropy = anisotropy;
+        self
+    }
+
+    pub fn lod_bias(mut self, bias: f32) -> Self{
+        self.lod_bias = bias;
+        self
+    }
+
+    pub fn lod_range(mut self, min: f32, max: f32) -> Self{
+        self.min_lod = min;
+        self.max_lod = max;
+        self
+    }
+
+    /// Color returned outside the texture with `gl::CLAMP_TO_BORDER`.
+    pub fn border_color(mut self, color: [f32; 4]) -> Self{
+        self.border_color = color;
+        self
+    }
+
+    /// Compares against the reference value for shadow samplers with `func`, like `gl::LEQUAL`.
+    pub fn compare(mut self, func: u32) -> Self{
+        self.compare_func = Some(func);
+        self
+    }
+
+    pub fn build(self) -> Sampler{
+        let sampler = Sampler::new();
+        let id = sampler.id();
+        unsafe{
+            gl::SamplerParameteri(id, gl::TEXTURE_MIN_FILTER, self.min_filter as _);
+            gl::SamplerParameteri(id, gl::TEXTURE_MAG_FILTER, self.mag_filter as _);
+            for (pname, wrap) in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R].into_iter().zip(self.wrap){
+                gl::SamplerParameteri(id, pname, wrap as _);
+            }
+            gl::SamplerParameterf(id, gl::TEXTURE_LOD_BIAS, self.lod_bias);
+            gl::SamplerParameterf(id, gl::TEXTURE_MIN_LOD, self.min_lod);
+            gl::SamplerParameterf(id, gl::TEXTURE_MAX_LOD, self.max_lod);
+            gl::SamplerParameterfv(id, gl::TEXTURE_BORDER_COLOR, self.border_color.as_ptr());
+
+            match self.compare_func{
+                Some(func) => {
+                    gl::SamplerParameteri(id, gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as _);
+                    gl::SamplerParameteri(id, gl::TEXTURE_COMPARE_FUNC, func as _);
+                },
+                None => gl::SamplerParameteri(id, gl::TEXTURE_COMPARE_MODE, gl::NONE as _)
+            }
+
+            if self.max_anisotropy > 1.0 && (ext::has_extension("GL_ARB_texture_filter_anisotropic") || ext::has_extension("GL_EXT_texture_filter_anisotropic")){
+                let mut max = 1.0;
+                gl::GetFloatv(ext::MAX_TEXTURE_MAX_ANISOTROPY, &mut max);
+                gl::SamplerParameterf(id, ext::TEXTURE_MAX_ANISOTROPY, self.max_anisotropy.min(max));
+            }
+        }
+        sampler
+    }
+}
+
+impl Default for SamplerBuilder{
+    fn default() -> Self {
+        Self{
+            min_filter: gl::NEAREST_MIPMAP_LINEAR,
+            mag_filter: gl::LINEAR,
+            wrap: [gl::REPEAT; 3],
+            max_anisotropy: 1.0,
+            lod_bias: 0.0,
+            min_lod: -1000.0,
+            max_lod: 1000.0,
+            border_color: [0.0; 4],
+            compare_func: None
+        }
+    }
+}
@@ -1,9 +1,8 @@
-use std::{rc::Rc, borrow::Cow, mem::size_of, collections::HashMap};
+use std::{rc::Rc, cell::RefCell, borrow::Cow, any::type_name, collections::HashMap, error::Error, fmt::{self, Debug, Display, Formatter}};
 
-use gl::Uniform2d;
-use glam::{Mat4, Mat3, Vec2, Vec3, Vec4, Quat, IVec2, IVec3, IVec4, UVec2, UVec3, UVec4};
+use glam::{Mat4, Mat3, Mat2, Vec2, Vec3, Vec4, Quat, IVec2, IVec3, IVec4, UVec2, UVec3, UVec4, DMat4, DMat3, DMat2, DVec2, DVec3, DVec4};
 
-use crate::internal::{RawId, RawIdManager};
+use crate::{ext, internal::{RawId, RawIdManager}, parallel::PendingProgram, preprocessor::PreprocessError, texture::TextureUnits, transform_feedback::FeedbackBufferMode};
 
 #[derive(Clone)]
 pub struct Shader{
@@ -28,23 +27,107 @@ impl Shader{
     }
 
     pub fn assign_source(&mut self, src: &str){
+        if let Err(e) = self.try_assign_source(src){
+            panic!("Failed to Compile Shader: {}",e)
+        }
+    }
+
+    /// Compiles `src`, returning the info log on failure instead of panicking.
+    pub fn try_assign_source(&mut self, src: &str) -> Result<(), ShaderError>{
+        self.submit_source(src);
+        self.compile_status()
+    }
+
+    /// Starts compiling `src` without waiting for the driver.
+    ///
+    /// Errors are reported when a program linked from the shader with
+    /// [`ProgramBuilder::build_async`] is resolved.
+    pub fn new_deferred(type_: u32, src: &str) -> Self{
+        let out = Self::new(type_);
+        out.submit_source(src);
+        out
+    }
+
+    fn submit_source(&self, src: &str){
         let src = Program::create_legal_str(src);
         unsafe{
             let v = [
                 src.as_bytes().as_ptr().cast()
             ];
-            gl::ShaderSource(self.id(),1,v.as_ptr(),0 as *const i32);
+            gl::ShaderSource(self.id(),1,v.as_ptr(),std::ptr::null());
             gl::CompileShader(self.id());
+        }
+    }
+
+    /// Waits for the compilation and returns the info log if it failed.
+    pub(crate) fn compile_status(&self) -> Result<(), ShaderError>{
+        unsafe{
             let mut success = 0;
             gl::GetShaderiv(self.id(), gl::COMPILE_STATUS, &mut success);
             if success == gl::FALSE as _{
-                let mut buf = [0;512];
-                let mut len = 0;
-                gl::GetShaderInfoLog(self.id(), 512, &mut len, buf.as_mut_ptr().cast());
-                let str = String::from_utf8_lossy(&buf[0..len as usize]);
-                panic!("Failed to Compile Shader: {}",str)
-            }       
+                return Err(ShaderError::Compile(self.info_log()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates a shader from a SPIR-V module, requires GL 4.6 or ARB_gl_spirv.
+    ///
+    /// `specialization_constants` are pairs of constant ids and the bits of their values,
+    /// use `f32::to_bits` for float constants. Modules of either byte order are accepted.
+    pub fn new_from_spirv(type_: u32, spirv: &[u8], entry_point: &str, specialization_constants: &[(u32, u32)]) -> Result<Self, ShaderError>{
+        const MAGIC: u32 = 0x07230203;
+        if !spirv.len().is_multiple_of(4){
+            return Err(ShaderError::InvalidSpirv("size isn't a multiple of 4 bytes"));
+        }
+        let swapped: Vec<u8>;
+        let spirv = match spirv.get(0..4).map(|x| u32::from_ne_bytes(x.try_into().unwrap())){
+            Some(MAGIC) => spirv,
+            Some(x) if x == MAGIC.swap_bytes() => {
+                swapped = spirv.chunks_exact(4).flat_map(|x| [x[3], x[2], x[1], x[0]]).collect();
+                &swapped
+            },
+            _ => return Err(ShaderError::InvalidSpirv("missing the SPIR-V magic number"))
+        };
+
+        if !ext::supports_spirv(){
+            return Err(ShaderError::Unsupported("SPIR-V shaders need GL 4.6 or ARB_gl_spirv"));
+        }
+
+        let out = Self::new(type_);
+        let entry_point = Program::create_legal_str(entry_point);
+        let (indices, values): (Vec<u32>, Vec<u32>) = specialization_constants.iter().copied().unzip();
+        unsafe{
+            gl::ShaderBinary(1, &out.id(), ext::SHADER_BINARY_FORMAT_SPIR_V, spirv.as_ptr().cast(), spirv.len() as i32);
+            ext::specialize_shader(
+                out.id(),
+                entry_point.as_ptr().cast(),
+                indices.len() as u32,
+                indices.as_ptr(),
+                values.as_ptr()
+            );
+
+            let mut success = 0;
+            gl::GetShaderiv(out.id(), gl::COMPILE_STATUS, &mut success);
+            if success == gl::FALSE as _{
+                return Err(ShaderError::Specialization(out.info_log()));
+            }
         }
+        Ok(out)
+    }
+
+    pub fn info_log(&self) -> String{
+        unsafe{
+            let mut len = 0;
+            gl::GetShaderiv(self.id(), gl::INFO_LOG_LENGTH, &mut len);
+            let mut buf = vec![0u8; len.max(1) as usize];
+            gl::GetShaderInfoLog(self.id(), len, &mut len, buf.as_mut_ptr().cast());
+            String::from_utf8_lossy(&buf[0..len as usize]).to_string()
+        }
+    }
+
+    pub fn shader_type(&self) -> u32{
+        self.type_
     }
 
     fn id(&self) -> u32{
@@ -66,32 +149,141 @@ impl RawIdManager for Shader{
 
 
 
+#[derive(Debug, Clone)]
+pub enum ShaderError{
+    /// The info log of a shader that failed to compile.
+    Compile(String),
+    /// The info log of a program that failed to link.
+    Link(String),
+    /// The info log of a program that failed validation.
+    Validate(String),
+    /// The info log of a SPIR-V shader that failed to specialize.
+    Specialization(String),
+    /// The data passed as a SPIR-V module isn't one.
+    InvalidSpirv(&'static str),
+    /// The driver lacks a feature that was used.
+    Unsupported(&'static str),
+    Preprocess(PreprocessError),
+}
+
+impl Display for ShaderError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self{
+            ShaderError::Compile(log) => write!(f, "Failed to compile shader: {}", log),
+            ShaderError::Link(log) => write!(f, "Failed to link program: {}", log),
+            ShaderError::Validate(log) => write!(f, "Failed to validate program: {}", log),
+            ShaderError::Specialization(log) => write!(f, "Failed to specialize shader: {}", log),
+            ShaderError::InvalidSpirv(what) => write!(f, "Invalid SPIR-V module: {}", what),
+            ShaderError::Unsupported(what) => write!(f, "Unsupported: {}", what),
+            ShaderError::Preprocess(e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl Error for ShaderError{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self{
+            ShaderError::Preprocess(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<PreprocessError> for ShaderError{
+    fn from(x: PreprocessError) -> Self {
+        Self::Preprocess(x)
+    }
+}
+
+#[derive(Clone)]
 pub struct Program{
     id: Rc<RawId<Self>>,
-    vertex_shader: Shader,
-    fragment_shader: Shader,
+    uniforms: Rc<ProgramUniforms>,
+    uniform_cache: Rc<UniformCache<Uniform>>,
+    shaders: Vec<Shader>,
 }
 
 impl Program{
     pub fn new(vertex_shader: Shader, fragment_shader: Shader) -> Self{
+        match Self::try_new(vertex_shader, fragment_shader){
+            Ok(x) => x,
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    /// Links the shaders, returning the info log on failure instead of panicking.
+    pub fn try_new(vertex_shader: Shader, fragment_shader: Shader) -> Result<Self, ShaderError>{
+        Self::builder()
+            .shader(vertex_shader)
+            .shader(fragment_shader)
+            .build()
+    }
+
+    pub fn builder() -> ProgramBuilder{
+        ProgramBuilder::new()
+    }
+
+    /// Loads a binary returned by [`Program::get_binary`].
+    ///
+    /// Drivers reject binaries built by other versions or hardware, in that case the
+    /// link error is returned and the program has to be built from source.
+    pub fn from_binary(format: u32, binary: &[u8]) -> Result<Self, ShaderError>{
         unsafe{
             let id = Rc::new(RawId::new());
+            gl::ProgramBinary(id.id(), format, binary.as_ptr().cast(), binary.len() as i32);
+            Self::from_linked(id, Vec::new())
+        }
+    }
 
-            gl::AttachShader(id.id(), vertex_shader.id());
-            gl::AttachShader(id.id(),fragment_shader.id());
-            gl::LinkProgram(id.id());
+    /// The driver specific binary of the program and its format, `None` if the driver
+    /// doesn't support program binaries.
+    ///
+    /// Build the program with [`ProgramBuilder::binary_retrievable`] to make sure this succeeds.
+    pub fn get_binary(&self) -> Option<(u32, Vec<u8>)>{
+        unsafe{
+            let mut len = 0;
+            gl::GetProgramiv(self.id.id(), gl::PROGRAM_BINARY_LENGTH, &mut len);
+            if len <= 0{
+                return None;
+            }
+
+            let mut binary = vec![0u8; len as usize];
+            let mut format = 0;
+            gl::GetProgramBinary(self.id.id(), len, &mut len, &mut format, binary.as_mut_ptr().cast());
+            binary.truncate(len as usize);
+            Some((format, binary))
+        }
+    }
+
+    /// Links a single stage program for use in a [`ProgramPipeline`](crate::pipeline::ProgramPipeline).
+    pub fn new_separable(shader: Shader) -> Result<Self, ShaderError>{
+        Self::builder()
+            .shader(shader)
+            .separable(true)
+            .build()
+    }
 
-            Self::check_status(id.id(), gl::LINK_STATUS, "LinkStatus");
+    /// Checks the link and validate status of a program that was just linked.
+    ///
+    /// Separable programs are validated as part of their pipeline instead.
+    pub(crate) fn from_linked(id: Rc<RawId<Self>>, shaders: Vec<Shader>) -> Result<Self, ShaderError>{
+        unsafe{
+            Self::check_status(id.id(), gl::LINK_STATUS).map_err(ShaderError::Link)?;
 
-            gl::ValidateProgram(id.id());
+            let mut separable = 0;
+            gl::GetProgramiv(id.id(), gl::PROGRAM_SEPARABLE, &mut separable);
+            if separable == gl::FALSE as _{
+                gl::ValidateProgram(id.id());
 
-            Self::check_status(id.id(), gl::VALIDATE_STATUS, "ValidateStatus");
+                Self::check_status(id.id(), gl::VALIDATE_STATUS).map_err(ShaderError::Validate)?;
+            }
 
-            Self{
-                fragment_shader,
-                vertex_shader,
+            Ok(Self{
+                uniforms: Rc::new(ProgramUniforms::query(id.clone())),
+                uniform_cache: Rc::new(UniformCache::default()),
+                shaders,
                 id
-            }
+            })
         }
     }
 
@@ -101,6 +293,15 @@ impl Program{
         }
     }
 
+    pub(crate) fn id(&self) -> u32{
+        self.id.id()
+    }
+
+    /// The shaders the program was linked from, empty for programs loaded from a binary.
+    pub fn shaders(&self) -> &[Shader]{
+        &self.shaders
+    }
+
     pub fn get_attributes(&self) -> HashMap<String,i32>{
         unsafe{
             let mut count = 0;
@@ -126,43 +327,48 @@ impl Program{
     }
 
     pub fn get_uniform(&self, name: &str) -> Option<Uniform>{
+        self.try_get_uniform(name).ok()
+    }
 
-        unsafe{
-            let name = Self::create_legal_str(name);
-            let id = gl::GetUniformLocation(self.id.id(), name.as_ptr().cast());
-
-            if id < 0{
-                return None;
-            }
+    /// The active `image*` uniforms, [`Uniform::binding`] tells which unit each reads from.
+    pub fn image_uniforms(&self) -> Vec<Uniform>{
+        self.uniforms.active.iter()
+            .filter(|x| UniformType::try_from(x.type_).is_ok_and(UniformType::is_image))
+            .filter_map(|x| self.uniforms.resolve(x.name.clone()).ok())
+            .collect()
+    }
 
-            let mut name_buf = [0;512];
-            let mut name_len = 0;
-            let mut size = 0;
-            let mut type_ = 0;
+    /// Looks up a uniform by its GLSL name.
+    ///
+    /// Besides plain uniforms this resolves array elements (`values[2]`), struct members
+    /// (`light.color`) and aggregates (`lights`, `lights[3]`) that can be navigated with
+    /// [`Uniform::index`] and [`Uniform::field`].
+    pub fn try_get_uniform(&self, name: &str) -> Result<Uniform, UniformError>{
+        self.uniforms.resolve(name.to_string())
+    }
 
-            gl::GetActiveUniform(self.id.id(), id as _, 512, &mut name_len, &mut size, &mut type_, name_buf.as_mut_ptr());
-            
-            Some(Uniform{
-                id: id as _,
-                size: size as _,
-                type_: type_.into()
-            })
-        }
+    /// Calls `f` with the uniform `name` if the program has it.
+    ///
+    /// Lookups are cached, this is what lets containers resolve their uniforms lazily per program.
+    pub fn with_cached_uniform<R>(&self, name: &str, f: impl FnOnce(&Uniform) -> R) -> Option<R>{
+        self.uniform_cache.get(name, |name| self.get_uniform(name)).as_ref().map(f)
     }
 
-    fn check_status(program: u32, pname: u32, name: &str){
+    /// Returns the info log if `pname` of `program` is false.
+    fn check_status(program: u32, pname: u32) -> Result<(), String>{
         unsafe{
             let mut success = 0;
             gl::GetProgramiv(program, pname, &mut success);
             if success == gl::FALSE as _{
-                let mut buf = [0;512];
                 let mut len = 0;
-                gl::GetProgramInfoLog(program,512,&mut len, buf.as_mut_ptr().cast());
+                gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
+                let mut buf = vec![0u8; len.max(1) as usize];
+                gl::GetProgramInfoLog(program,len,&mut len, buf.as_mut_ptr().cast());
 
-                let str = String::from_utf8_lossy(&buf[0..len as usize]);
-                panic!("Check of {} for a program failed: Error: {}",name,str);
+                return Err(String::from_utf8_lossy(&buf[0..len as usize]).to_string());
             }
         }
+        Ok(())
     }
 
     fn create_legal_str<'a>(value: &'a str) -> Cow<'a, str>{
@@ -178,6 +384,80 @@ impl Program{
     }
 }
 
+/// Collects the shaders and link options of a [`Program`].
+#[derive(Default)]
+pub struct ProgramBuilder{
+    shaders: Vec<Shader>,
+    binary_retrievable: bool,
+    separable: bool,
+    feedback_varyings: Option<(Vec<String>, FeedbackBufferMode)>,
+}
+
+impl ProgramBuilder{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    pub fn shader(mut self, shader: Shader) -> Self{
+        self.shaders.push(shader);
+        self
+    }
+
+    /// Hints the driver that [`Program::get_binary`] will be used.
+    pub fn binary_retrievable(mut self, retrievable: bool) -> Self{
+        self.binary_retrievable = retrievable;
+        self
+    }
+
+    /// Allows the program to be combined with others in a [`ProgramPipeline`](crate::pipeline::ProgramPipeline).
+    pub fn separable(mut self, separable: bool) -> Self{
+        self.separable = separable;
+        self
+    }
+
+    /// Captures the vertex shader outputs `varyings` into transform feedback buffers,
+    /// all into one buffer or one buffer each depending on `mode`.
+    pub fn transform_feedback_varyings(mut self, varyings: &[&str], mode: FeedbackBufferMode) -> Self{
+        self.feedback_varyings = Some((varyings.iter().map(|x| x.to_string()).collect(), mode));
+        self
+    }
+
+    pub fn build(self) -> Result<Program, ShaderError>{
+        let (id, shaders) = self.link();
+        Program::from_linked(id, shaders)
+    }
+
+    /// Links without waiting for the driver, see [`PendingProgram`].
+    pub fn build_async(self) -> PendingProgram{
+        let (id, shaders) = self.link();
+        PendingProgram::new(id, shaders)
+    }
+
+    fn link(self) -> (Rc<RawId<Program>>, Vec<Shader>){
+        unsafe{
+            let id = Rc::new(RawId::<Program>::new());
+
+            for shader in &self.shaders{
+                gl::AttachShader(id.id(), shader.id());
+            }
+            if self.binary_retrievable{
+                gl::ProgramParameteri(id.id(), gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as _);
+            }
+            if self.separable{
+                gl::ProgramParameteri(id.id(), gl::PROGRAM_SEPARABLE, gl::TRUE as _);
+            }
+            if let Some((varyings, mode)) = &self.feedback_varyings{
+                let names: Vec<_> = varyings.iter().map(|x| Program::create_legal_str(x)).collect();
+                let ptrs: Vec<*const gl::types::GLchar> = names.iter().map(|x| x.as_ptr().cast()).collect();
+                gl::TransformFeedbackVaryings(id.id(), ptrs.len() as _, ptrs.as_ptr(), *mode as u32);
+            }
+            gl::LinkProgram(id.id());
+
+            (id, self.shaders)
+        }
+    }
+}
+
 impl RawIdManager for Program{
     fn create_resource() -> u32 {
         unsafe{
@@ -192,13 +472,187 @@ impl RawIdManager for Program{
     }    
 }
 
+/// Results of looking uniforms up by name, including the names a program doesn't have.
+struct UniformCache<T>{
+    entries: RefCell<HashMap<String, Option<T>>>,
+}
+
+impl<T> Default for UniformCache<T>{
+    fn default() -> Self {
+        Self{
+            entries: RefCell::new(HashMap::new())
+        }
+    }
+}
+
+impl<T: Clone> UniformCache<T>{
+    /// Returns the cached result for `name`, calling `lookup` the first time.
+    ///
+    /// The result is cloned out so the caller can use the program, including this cache.
+    fn get(&self, name: &str, lookup: impl FnOnce(&str) -> Option<T>) -> Option<T>{
+        if let Some(cached) = self.entries.borrow().get(name){
+            return cached.clone();
+        }
+        let found = lookup(name);
+        self.entries.borrow_mut().insert(name.to_string(), found.clone());
+        found
+    }
+}
+
+/// The active uniforms of a linked program, used to resolve uniform names.
+struct ProgramUniforms{
+    program: Rc<RawId<Program>>,
+    active: Vec<ActiveUniform>,
+}
+
+struct ActiveUniform{
+    /// Name as reported by the driver, arrays of basic types end with `[0]`.
+    name: String,
+    /// Active uniform index, for `glGetActiveUniformsiv`.
+    index: u32,
+    /// -1 for atomic counters.
+    location: i32,
+    size: i32,
+    type_: u32,
+}
+
+impl ProgramUniforms{
+    fn query(program: Rc<RawId<Program>>) -> Self{
+        unsafe{
+            let mut count = 0;
+            let mut max_len = 0;
+            gl::GetProgramiv(program.id(), gl::ACTIVE_UNIFORMS, &mut count);
+            gl::GetProgramiv(program.id(), gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_len);
+
+            let mut active = Vec::with_capacity(count as usize);
+            let mut name_buf = vec![0u8; max_len.max(1) as usize];
+
+            for x in 0..count{
+                let mut name_len = 0;
+                let mut size = 0;
+                let mut type_ = 0;
+                gl::GetActiveUniform(program.id(), x as _, max_len, &mut name_len, &mut size, &mut type_, name_buf.as_mut_ptr().cast());
+
+                let name = String::from_utf8_lossy(&name_buf[0..name_len as usize]).to_string();
+                let location = gl::GetUniformLocation(program.id(), Program::create_legal_str(&name).as_ptr().cast());
+
+                // members of uniform blocks have no location, neither do atomic counters,
+                // which are kept so they can report their buffer binding
+                if location < 0 && type_ != gl::UNSIGNED_INT_ATOMIC_COUNTER{
+                    continue;
+                }
+
+                active.push(ActiveUniform{
+                    name,
+                    index: x as u32,
+                    location,
+                    size,
+                    type_
+                });
+            }
+
+            Self{
+                program,
+                active
+            }
+        }
+    }
+
+    fn resolve(self: &Rc<Self>, name: String) -> Result<Uniform, UniformError>{
+        let leaf = |location: i32, size: i32, type_: u32, name: String| -> Result<Uniform, UniformError>{
+            Ok(Uniform{
+                id: location,
+                size,
+                type_: Some(type_.try_into()?),
+                name,
+                uniforms: self.clone()
+            })
+        };
+
+        for active in &self.active{
+            if active.name == name{
+                return leaf(active.location, active.size, active.type_, name);
+            }
+            if active.name.strip_suffix("[0]") == Some(name.as_str()){
+                return leaf(active.location, active.size, active.type_, name);
+            }
+        }
+
+        // an element of an array of basic types, addresses the rest of the array
+        if let Some((base, index)) = Self::split_index(&name){
+            let array_name = format!("{}[0]", base);
+            if let Some(active) = self.active.iter().find(|x| x.name == array_name){
+                if index >= active.size as usize{
+                    return Err(UniformError::NotFound(name));
+                }
+                let location = match active.type_{
+                    gl::UNSIGNED_INT_ATOMIC_COUNTER => -1,
+                    _ => unsafe{
+                        let location = gl::GetUniformLocation(self.program.id(), Program::create_legal_str(&name).as_ptr().cast());
+                        if location < 0{
+                            return Err(UniformError::NotFound(name));
+                        }
+                        location
+                    }
+                };
+                return leaf(location, active.size - index as i32, active.type_, name);
+            }
+        }
+
+        let is_aggregate = self.active.iter().any(|x| {
+            x.name.strip_prefix(name.as_str())
+                .is_some_and(|rest| rest.starts_with('.') || rest.starts_with('['))
+        });
+
+        if is_aggregate{
+            Ok(Uniform{
+                id: -1,
+                size: 0,
+                type_: None,
+                name,
+                uniforms: self.clone()
+            })
+        }
+        else{
+            Err(UniformError::NotFound(name))
+        }
+    }
+
+    /// The `ATOMIC_COUNTER_BUFFER` binding the atomic counter `name` (or an element of it) reads from.
+    fn counter_binding(&self, name: &str) -> Option<i32>{
+        let base = Self::split_index(name).map_or(name, |(base, _)| base);
+        let active = self.active.iter()
+            .find(|x| x.name == name || x.name.strip_suffix("[0]") == Some(base))?;
+        unsafe{
+            let mut buffer = 0;
+            gl::GetActiveUniformsiv(self.program.id(), 1, &active.index, gl::UNIFORM_ATOMIC_COUNTER_BUFFER_INDEX, &mut buffer);
+            let mut binding = 0;
+            gl::GetActiveAtomicCounterBufferiv(self.program.id(), buffer as u32, gl::ATOMIC_COUNTER_BUFFER_BINDING, &mut binding);
+            Some(binding)
+        }
+    }
 
+    /// Splits `name[index]` into `name` and `index`.
+    fn split_index(name: &str) -> Option<(&str, usize)>{
+        let rest = name.strip_suffix(']')?;
+        let open = rest.rfind('[')?;
+        let index = rest[open + 1..].parse().ok()?;
+        Some((&rest[..open], index))
+    }
+}
+
+/// A uniform of a program, or a struct / array of structs that contains uniforms.
+#[derive(Clone)]
 pub struct Uniform{
     id: i32,
     size: i32,
-    type_: UniformType
+    /// `None` for structs and arrays of structs, these can only be navigated.
+    type_: Option<UniformType>,
+    name: String,
+    uniforms: Rc<ProgramUniforms>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum UniformType{
     Int,
     IntVec2,
@@ -250,80 +704,404 @@ pub enum UniformType{
     Sampler1DArrayShadow,
     Sampler2DArrayShadow,
     Sampler2DMultiSample,
-    Sampler2DMultiSampleArray
+    Sampler2DMultiSampleArray,
+    SamplerCubeShadow,
+    SamplerCubeMapArray,
+    SamplerCubeMapArrayShadow,
+    SamplerBuffer,
+    Sampler2DRect,
+    Sampler2DRectShadow,
+    IntSampler1D,
+    IntSampler2D,
+    IntSampler3D,
+    IntSamplerCube,
+    IntSampler1DArray,
+    IntSampler2DArray,
+    IntSamplerCubeMapArray,
+    IntSampler2DMultiSample,
+    IntSampler2DMultiSampleArray,
+    IntSamplerBuffer,
+    IntSampler2DRect,
+    UIntSampler1D,
+    UIntSampler2D,
+    UIntSampler3D,
+    UIntSamplerCube,
+    UIntSampler1DArray,
+    UIntSampler2DArray,
+    UIntSamplerCubeMapArray,
+    UIntSampler2DMultiSample,
+    UIntSampler2DMultiSampleArray,
+    UIntSamplerBuffer,
+    UIntSampler2DRect,
+    Image1D,
+    Image2D,
+    Image3D,
+    ImageCube,
+    Image1DArray,
+    Image2DArray,
+    ImageCubeMapArray,
+    Image2DMultiSample,
+    Image2DMultiSampleArray,
+    ImageBuffer,
+    Image2DRect,
+    IntImage1D,
+    IntImage2D,
+    IntImage3D,
+    IntImageCube,
+    IntImage1DArray,
+    IntImage2DArray,
+    IntImageCubeMapArray,
+    IntImage2DMultiSample,
+    IntImage2DMultiSampleArray,
+    IntImageBuffer,
+    IntImage2DRect,
+    UIntImage1D,
+    UIntImage2D,
+    UIntImage3D,
+    UIntImageCube,
+    UIntImage1DArray,
+    UIntImage2DArray,
+    UIntImageCubeMapArray,
+    UIntImage2DMultiSample,
+    UIntImage2DMultiSampleArray,
+    UIntImageBuffer,
+    UIntImage2DRect,
+    UIntAtomicCounter
 }
 
 impl Uniform{
-    pub fn set_uniform<T>(&self, data: &dyn UniformValid<T>)
+    /// Writes `data` to this uniform, the program doesn't have to be bound.
+    ///
+    /// Fails if `data` doesn't match the reflected type or holds more elements than the uniform.
+    pub fn set_uniform<V: UniformValid + ?Sized>(&self, data: &V) -> Result<(), UniformError>
     {
-        if size_of::<T>() % 4 != 0{
-            panic!("the size of type must be a multiple of 4");
-        } 
+        let type_ = self.type_.ok_or_else(|| UniformError::Aggregate(self.name.clone()))?;
+        if !V::matches(type_){
+            return Err(UniformError::TypeMismatch{
+                expected: type_,
+                found: type_name::<V>()
+            });
+        }
+        let count = data.element_count();
+        if count > self.size as usize{
+            return Err(UniformError::TooManyElements{
+                size: self.size,
+                count
+            });
+        }
+        let count = count as i32;
+        let program = self.uniforms.program.id();
         unsafe{
             let uptr = data.get_ptr().cast();
             let iptr = data.get_ptr().cast();
             let fptr = data.get_ptr().cast();
             let dptr = data.get_ptr().cast();
-            match self.type_ {
-                UniformType::Int => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::IntVec2 => gl::Uniform2iv(self.id,self.size,iptr),
-                UniformType::IntVec3 => gl::Uniform3iv(self.id,self.size,iptr),
-                UniformType::IntVec4 => gl::Uniform4iv(self.id,self.size,iptr),
-                UniformType::UInt => gl::Uniform1uiv(self.id,self.size,uptr),
-                UniformType::UIntVec2 => gl::Uniform2uiv(self.id,self.size,uptr),
-                UniformType::UIntVec3 => gl::Uniform3uiv(self.id,self.size,uptr),
-                UniformType::UIntVec4 => gl::Uniform4uiv(self.id,self.size,uptr),
-                UniformType::Bool => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::BoolVec2 => gl::Uniform2iv(self.id,self.size,iptr),
-                UniformType::BoolVec3 => gl::Uniform3iv(self.id,self.size,iptr),
-                UniformType::BoolVec4 => gl::Uniform4iv(self.id,self.size,iptr),
-                UniformType::Float => gl::Uniform1fv(self.id,self.size,fptr),
-                UniformType::FloatVec2 => gl::Uniform2fv(self.id,self.size,fptr),
-                UniformType::FloatVec3 => gl::Uniform3fv(self.id,self.size,fptr),
-                UniformType::FloatVec4 => gl::Uniform4fv(self.id,self.size,fptr),
-                UniformType::FloatMat2 => gl::UniformMatrix2fv(self.id,self.size, gl::FALSE,fptr),
-                UniformType::FloatMat3 => gl::UniformMatrix3fv(self.id,self.size, gl::FALSE,fptr),
-                UniformType::FloatMat4 => gl::UniformMatrix4fv(self.id,self.size, gl::FALSE,fptr),
-                UniformType::FloatMat2x3 => gl::UniformMatrix2x3fv(self.id,self.size, gl::FALSE,fptr),
-                UniformType::FloatMat2x4 => gl::UniformMatrix2x4fv(self.id,self.size, gl::FALSE,fptr),
-                UniformType::FloatMat3x2 => gl::UniformMatrix3x2fv(self.id,self.size, gl::FALSE,fptr),
-                UniformType::FloatMat3x4 => gl::UniformMatrix3x4fv(self.id,self.size, gl::FALSE,fptr),
-                UniformType::FloatMat4x2 => gl::UniformMatrix4x2fv(self.id,self.size, gl::FALSE,fptr),
-                UniformType::FloatMat4x3 => gl::UniformMatrix4x3fv(self.id,self.size, gl::FALSE,fptr),
-                UniformType::Double => gl::Uniform1dv(self.id,self.size,dptr),
-                UniformType::DoubleVec1 => gl::Uniform1dv(self.id,self.size,dptr),
-                UniformType::DoubleVec2 => gl::Uniform2dv(self.id,self.size,dptr),
-                UniformType::DoubleVec3 => gl::Uniform3dv(self.id,self.size,dptr),
-                UniformType::DoubleVec4 => gl::Uniform4dv(self.id,self.size,dptr),
-                UniformType::DoubleMat2 => gl::UniformMatrix2dv(self.id, self.size, gl::FALSE, dptr),
-                UniformType::DoubleMat3 => gl::UniformMatrix3dv(self.id, self.size, gl::FALSE, dptr),
-                UniformType::DoubleMat4 => gl::UniformMatrix4dv(self.id, self.size, gl::FALSE, dptr),
-                UniformType::DoubleMat2x3 => gl::UniformMatrix2x3dv(self.id, self.size, gl::FALSE, dptr),
-                UniformType::DoubleMat2x4 => gl::UniformMatrix2x4dv(self.id, self.size, gl::FALSE, dptr),
-                UniformType::DoubleMat3x2 => gl::UniformMatrix3x2dv(self.id, self.size, gl::FALSE, dptr),
-                UniformType::DoubleMat3x4 => gl::UniformMatrix3x4dv(self.id, self.size, gl::FALSE, dptr),
-                UniformType::DoubleMat4x2 => gl::UniformMatrix4x2dv(self.id, self.size, gl::FALSE, dptr),
-                UniformType::DoubleMat4x3 => gl::UniformMatrix4x3dv(self.id, self.size, gl::FALSE, dptr),
-                UniformType::Sampler1D => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::Sampler2D => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::Sampler3D => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::SamplerCube => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::Sampler1DShadow => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::Sampler2DShadow => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::Sampler1DArray => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::Sampler2DArray => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::Sampler1DArrayShadow => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::Sampler2DArrayShadow => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::Sampler2DMultiSample => gl::Uniform1iv(self.id,self.size,iptr),
-                UniformType::Sampler2DMultiSampleArray => gl::Uniform1iv(self.id,self.size,iptr),
+            match type_ {
+                UniformType::Int => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::IntVec2 => gl::ProgramUniform2iv(program,self.id,count,iptr),
+                UniformType::IntVec3 => gl::ProgramUniform3iv(program,self.id,count,iptr),
+                UniformType::IntVec4 => gl::ProgramUniform4iv(program,self.id,count,iptr),
+                UniformType::UInt => gl::ProgramUniform1uiv(program,self.id,count,uptr),
+                UniformType::UIntVec2 => gl::ProgramUniform2uiv(program,self.id,count,uptr),
+                UniformType::UIntVec3 => gl::ProgramUniform3uiv(program,self.id,count,uptr),
+                UniformType::UIntVec4 => gl::ProgramUniform4uiv(program,self.id,count,uptr),
+                UniformType::Bool => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::BoolVec2 => gl::ProgramUniform2iv(program,self.id,count,iptr),
+                UniformType::BoolVec3 => gl::ProgramUniform3iv(program,self.id,count,iptr),
+                UniformType::BoolVec4 => gl::ProgramUniform4iv(program,self.id,count,iptr),
+                UniformType::Float => gl::ProgramUniform1fv(program,self.id,count,fptr),
+                UniformType::FloatVec2 => gl::ProgramUniform2fv(program,self.id,count,fptr),
+                UniformType::FloatVec3 => gl::ProgramUniform3fv(program,self.id,count,fptr),
+                UniformType::FloatVec4 => gl::ProgramUniform4fv(program,self.id,count,fptr),
+                UniformType::FloatMat2 => gl::ProgramUniformMatrix2fv(program,self.id,count, gl::FALSE,fptr),
+                UniformType::FloatMat3 => gl::ProgramUniformMatrix3fv(program,self.id,count, gl::FALSE,fptr),
+                UniformType::FloatMat4 => gl::ProgramUniformMatrix4fv(program,self.id,count, gl::FALSE,fptr),
+                UniformType::FloatMat2x3 => gl::ProgramUniformMatrix2x3fv(program,self.id,count, gl::FALSE,fptr),
+                UniformType::FloatMat2x4 => gl::ProgramUniformMatrix2x4fv(program,self.id,count, gl::FALSE,fptr),
+                UniformType::FloatMat3x2 => gl::ProgramUniformMatrix3x2fv(program,self.id,count, gl::FALSE,fptr),
+                UniformType::FloatMat3x4 => gl::ProgramUniformMatrix3x4fv(program,self.id,count, gl::FALSE,fptr),
+                UniformType::FloatMat4x2 => gl::ProgramUniformMatrix4x2fv(program,self.id,count, gl::FALSE,fptr),
+                UniformType::FloatMat4x3 => gl::ProgramUniformMatrix4x3fv(program,self.id,count, gl::FALSE,fptr),
+                UniformType::Double => gl::ProgramUniform1dv(program,self.id,count,dptr),
+                UniformType::DoubleVec1 => gl::ProgramUniform1dv(program,self.id,count,dptr),
+                UniformType::DoubleVec2 => gl::ProgramUniform2dv(program,self.id,count,dptr),
+                UniformType::DoubleVec3 => gl::ProgramUniform3dv(program,self.id,count,dptr),
+                UniformType::DoubleVec4 => gl::ProgramUniform4dv(program,self.id,count,dptr),
+                UniformType::DoubleMat2 => gl::ProgramUniformMatrix2dv(program,self.id, count, gl::FALSE, dptr),
+                UniformType::DoubleMat3 => gl::ProgramUniformMatrix3dv(program,self.id, count, gl::FALSE, dptr),
+                UniformType::DoubleMat4 => gl::ProgramUniformMatrix4dv(program,self.id, count, gl::FALSE, dptr),
+                UniformType::DoubleMat2x3 => gl::ProgramUniformMatrix2x3dv(program,self.id, count, gl::FALSE, dptr),
+                UniformType::DoubleMat2x4 => gl::ProgramUniformMatrix2x4dv(program,self.id, count, gl::FALSE, dptr),
+                UniformType::DoubleMat3x2 => gl::ProgramUniformMatrix3x2dv(program,self.id, count, gl::FALSE, dptr),
+                UniformType::DoubleMat3x4 => gl::ProgramUniformMatrix3x4dv(program,self.id, count, gl::FALSE, dptr),
+                UniformType::DoubleMat4x2 => gl::ProgramUniformMatrix4x2dv(program,self.id, count, gl::FALSE, dptr),
+                UniformType::DoubleMat4x3 => gl::ProgramUniformMatrix4x3dv(program,self.id, count, gl::FALSE, dptr),
+                UniformType::Sampler1D => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::Sampler2D => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::Sampler3D => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::SamplerCube => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::Sampler1DShadow => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::Sampler2DShadow => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::Sampler1DArray => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::Sampler2DArray => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::Sampler1DArrayShadow => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::Sampler2DArrayShadow => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::Sampler2DMultiSample => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::Sampler2DMultiSampleArray => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::SamplerCubeShadow
+                | UniformType::SamplerCubeMapArray
+                | UniformType::SamplerCubeMapArrayShadow
+                | UniformType::SamplerBuffer
+                | UniformType::Sampler2DRect
+                | UniformType::Sampler2DRectShadow => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::IntSampler1D
+                | UniformType::IntSampler2D
+                | UniformType::IntSampler3D
+                | UniformType::IntSamplerCube
+                | UniformType::IntSampler1DArray
+                | UniformType::IntSampler2DArray
+                | UniformType::IntSamplerCubeMapArray
+                | UniformType::IntSampler2DMultiSample
+                | UniformType::IntSampler2DMultiSampleArray
+                | UniformType::IntSamplerBuffer
+                | UniformType::IntSampler2DRect => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::UIntSampler1D
+                | UniformType::UIntSampler2D
+                | UniformType::UIntSampler3D
+                | UniformType::UIntSamplerCube
+                | UniformType::UIntSampler1DArray
+                | UniformType::UIntSampler2DArray
+                | UniformType::UIntSamplerCubeMapArray
+                | UniformType::UIntSampler2DMultiSample
+                | UniformType::UIntSampler2DMultiSampleArray
+                | UniformType::UIntSamplerBuffer
+                | UniformType::UIntSampler2DRect => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                UniformType::Image1D
+                | UniformType::Image2D
+                | UniformType::Image3D
+                | UniformType::ImageCube
+                | UniformType::Image1DArray
+                | UniformType::Image2DArray
+                | UniformType::ImageCubeMapArray
+                | UniformType::Image2DMultiSample
+                | UniformType::Image2DMultiSampleArray
+                | UniformType::ImageBuffer
+                | UniformType::Image2DRect
+                | UniformType::IntImage1D
+                | UniformType::IntImage2D
+                | UniformType::IntImage3D
+                | UniformType::IntImageCube
+                | UniformType::IntImage1DArray
+                | UniformType::IntImage2DArray
+                | UniformType::IntImageCubeMapArray
+                | UniformType::IntImage2DMultiSample
+                | UniformType::IntImage2DMultiSampleArray
+                | UniformType::IntImageBuffer
+                | UniformType::IntImage2DRect
+                | UniformType::UIntImage1D
+                | UniformType::UIntImage2D
+                | UniformType::UIntImage3D
+                | UniformType::UIntImageCube
+                | UniformType::UIntImage1DArray
+                | UniformType::UIntImage2DArray
+                | UniformType::UIntImageCubeMapArray
+                | UniformType::UIntImage2DMultiSample
+                | UniformType::UIntImage2DMultiSampleArray
+                | UniformType::UIntImageBuffer
+                | UniformType::UIntImage2DRect => gl::ProgramUniform1iv(program,self.id,count,iptr),
+                // atomic counters live in ATOMIC_COUNTER_BUFFER bindings, glUniform* rejects them
+                UniformType::UIntAtomicCounter => return Err(UniformError::NotAssignable(type_)),
             }
         }
+        Ok(())
+    }
+
+    /// Writes `data` to the array elements starting at `start`.
+    pub fn set_uniform_range<V>(&self, start: usize, data: &[V]) -> Result<(), UniformError> where [V]: UniformValid{
+        self.index(start)?.set_uniform(data)
+    }
+
+    /// Resolves the element `i` of an array uniform.
+    pub fn index(&self, i: usize) -> Result<Uniform, UniformError>{
+        self.uniforms.resolve(format!("{}[{}]", self.name, i))
+    }
+
+    /// Resolves the member `name` of a struct uniform.
+    pub fn field(&self, name: &str) -> Result<Uniform, UniformError>{
+        self.uniforms.resolve(format!("{}.{}", self.name, name))
+    }
+
+    pub fn name(&self) -> &str{
+        &self.name
+    }
+
+    /// True if the uniform was looked up in `program` or a clone of it.
+    pub fn belongs_to(&self, program: &Program) -> bool{
+        self.uniforms.program.id() == program.id.id()
+    }
+
+    /// The GLSL type, `None` for structs and arrays of structs.
+    pub fn uniform_type(&self) -> Option<UniformType>{
+        self.type_
+    }
+
+    /// Number of array elements from this uniform on, 1 for non-array uniforms.
+    pub fn size(&self) -> i32{
+        self.size
+    }
+
+    /// The texture or image unit a sampler or image uniform reads from, or the buffer binding
+    /// of an atomic counter, `None` for other types.
+    pub fn binding(&self) -> Option<i32>{
+        let type_ = self.type_?;
+        if type_ == UniformType::UIntAtomicCounter{
+            return self.uniforms.counter_binding(&self.name);
+        }
+        if !type_.is_sampler() && !type_.is_image(){
+            return None;
+        }
+        unsafe{
+            let mut unit = 0;
+            gl::GetUniformiv(self.uniforms.program.id(), self.id, &mut unit);
+            Some(unit)
+        }
     }
 }
 
-impl From<u32> for UniformType{
-    fn from(x: u32) -> Self {
-        match x{
+impl Debug for Uniform{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Uniform")
+            .field("name", &self.name)
+            .field("location", &self.id)
+            .field("size", &self.size)
+            .field("type_", &self.type_)
+            .finish()
+    }
+}
+
+impl UniformType{
+    /// Returns true for all `sampler*` types, including integer and shadow samplers.
+    pub fn is_sampler(self) -> bool{
+        matches!(
+            self,
+            Self::Sampler1D
+            | Self::Sampler2D
+            | Self::Sampler3D
+            | Self::SamplerCube
+            | Self::Sampler1DShadow
+            | Self::Sampler2DShadow
+            | Self::Sampler1DArray
+            | Self::Sampler2DArray
+            | Self::Sampler1DArrayShadow
+            | Self::Sampler2DArrayShadow
+            | Self::Sampler2DMultiSample
+            | Self::Sampler2DMultiSampleArray
+            | Self::SamplerCubeShadow
+            | Self::SamplerCubeMapArray
+            | Self::SamplerCubeMapArrayShadow
+            | Self::SamplerBuffer
+            | Self::Sampler2DRect
+            | Self::Sampler2DRectShadow
+            | Self::IntSampler1D
+            | Self::IntSampler2D
+            | Self::IntSampler3D
+            | Self::IntSamplerCube
+            | Self::IntSampler1DArray
+            | Self::IntSampler2DArray
+            | Self::IntSamplerCubeMapArray
+            | Self::IntSampler2DMultiSample
+            | Self::IntSampler2DMultiSampleArray
+            | Self::IntSamplerBuffer
+            | Self::IntSampler2DRect
+            | Self::UIntSampler1D
+            | Self::UIntSampler2D
+            | Self::UIntSampler3D
+            | Self::UIntSamplerCube
+            | Self::UIntSampler1DArray
+            | Self::UIntSampler2DArray
+            | Self::UIntSamplerCubeMapArray
+            | Self::UIntSampler2DMultiSample
+            | Self::UIntSampler2DMultiSampleArray
+            | Self::UIntSamplerBuffer
+            | Self::UIntSampler2DRect
+        )
+    }
+
+    /// Returns true for all `image*` types used with image load/store.
+    pub fn is_image(self) -> bool{
+        matches!(
+            self,
+            Self::Image1D
+            | Self::Image2D
+            | Self::Image3D
+            | Self::ImageCube
+            | Self::Image1DArray
+            | Self::Image2DArray
+            | Self::ImageCubeMapArray
+            | Self::Image2DMultiSample
+            | Self::Image2DMultiSampleArray
+            | Self::ImageBuffer
+            | Self::Image2DRect
+            | Self::IntImage1D
+            | Self::IntImage2D
+            | Self::IntImage3D
+            | Self::IntImageCube
+            | Self::IntImage1DArray
+            | Self::IntImage2DArray
+            | Self::IntImageCubeMapArray
+            | Self::IntImage2DMultiSample
+            | Self::IntImage2DMultiSampleArray
+            | Self::IntImageBuffer
+            | Self::IntImage2DRect
+            | Self::UIntImage1D
+            | Self::UIntImage2D
+            | Self::UIntImage3D
+            | Self::UIntImageCube
+            | Self::UIntImage1DArray
+            | Self::UIntImage2DArray
+            | Self::UIntImageCubeMapArray
+            | Self::UIntImage2DMultiSample
+            | Self::UIntImage2DMultiSampleArray
+            | Self::UIntImageBuffer
+            | Self::UIntImage2DRect
+        )
+    }
+
+    /// The texture target sampler and image types read from, `None` for other types.
+    pub fn texture_target(self) -> Option<u32>{
+        Some(match self{
+            Self::Sampler1D | Self::Sampler1DShadow | Self::IntSampler1D | Self::UIntSampler1D
+            | Self::Image1D | Self::IntImage1D | Self::UIntImage1D => gl::TEXTURE_1D,
+            Self::Sampler2D | Self::Sampler2DShadow | Self::IntSampler2D | Self::UIntSampler2D
+            | Self::Image2D | Self::IntImage2D | Self::UIntImage2D => gl::TEXTURE_2D,
+            Self::Sampler3D | Self::IntSampler3D | Self::UIntSampler3D
+            | Self::Image3D | Self::IntImage3D | Self::UIntImage3D => gl::TEXTURE_3D,
+            Self::SamplerCube | Self::SamplerCubeShadow | Self::IntSamplerCube | Self::UIntSamplerCube
+            | Self::ImageCube | Self::IntImageCube | Self::UIntImageCube => gl::TEXTURE_CUBE_MAP,
+            Self::Sampler1DArray | Self::Sampler1DArrayShadow | Self::IntSampler1DArray | Self::UIntSampler1DArray
+            | Self::Image1DArray | Self::IntImage1DArray | Self::UIntImage1DArray => gl::TEXTURE_1D_ARRAY,
+            Self::Sampler2DArray | Self::Sampler2DArrayShadow | Self::IntSampler2DArray | Self::UIntSampler2DArray
+            | Self::Image2DArray | Self::IntImage2DArray | Self::UIntImage2DArray => gl::TEXTURE_2D_ARRAY,
+            Self::SamplerCubeMapArray | Self::SamplerCubeMapArrayShadow | Self::IntSamplerCubeMapArray | Self::UIntSamplerCubeMapArray
+            | Self::ImageCubeMapArray | Self::IntImageCubeMapArray | Self::UIntImageCubeMapArray => gl::TEXTURE_CUBE_MAP_ARRAY,
+            Self::Sampler2DMultiSample | Self::IntSampler2DMultiSample | Self::UIntSampler2DMultiSample
+            | Self::Image2DMultiSample | Self::IntImage2DMultiSample | Self::UIntImage2DMultiSample => gl::TEXTURE_2D_MULTISAMPLE,
+            Self::Sampler2DMultiSampleArray | Self::IntSampler2DMultiSampleArray | Self::UIntSampler2DMultiSampleArray
+            | Self::Image2DMultiSampleArray | Self::IntImage2DMultiSampleArray | Self::UIntImage2DMultiSampleArray => gl::TEXTURE_2D_MULTISAMPLE_ARRAY,
+            Self::SamplerBuffer | Self::IntSamplerBuffer | Self::UIntSamplerBuffer
+            | Self::ImageBuffer | Self::IntImageBuffer | Self::UIntImageBuffer => gl::TEXTURE_BUFFER,
+            Self::Sampler2DRect | Self::Sampler2DRectShadow | Self::IntSampler2DRect | Self::UIntSampler2DRect
+            | Self::Image2DRect | Self::IntImage2DRect | Self::UIntImage2DRect => gl::TEXTURE_RECTANGLE,
+            _ => return None
+        })
+    }
+}
+
+impl TryFrom<u32> for UniformType{
+    type Error = UnknownUniformType;
+
+    fn try_from(x: u32) -> Result<Self, Self::Error> {
+        Ok(match x{
             gl::FLOAT => Self::Float,
             gl::FLOAT_VEC2 => Self::FloatVec2,
             gl::FLOAT_VEC3 => Self::FloatVec3,
@@ -381,40 +1159,226 @@ impl From<u32> for UniformType{
             gl::SAMPLER_2D_ARRAY_SHADOW => Self::Sampler2DArrayShadow,
             gl::SAMPLER_2D_MULTISAMPLE => Self::Sampler2DMultiSample,
             gl::SAMPLER_2D_MULTISAMPLE_ARRAY => Self::Sampler2DMultiSampleArray,
+            gl::SAMPLER_CUBE_SHADOW => Self::SamplerCubeShadow,
+            gl::SAMPLER_CUBE_MAP_ARRAY => Self::SamplerCubeMapArray,
+            gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW => Self::SamplerCubeMapArrayShadow,
+            gl::SAMPLER_BUFFER => Self::SamplerBuffer,
+            gl::SAMPLER_2D_RECT => Self::Sampler2DRect,
+            gl::SAMPLER_2D_RECT_SHADOW => Self::Sampler2DRectShadow,
 
-            x => panic!("Unexpected value {}",x)
+            gl::INT_SAMPLER_1D => Self::IntSampler1D,
+            gl::INT_SAMPLER_2D => Self::IntSampler2D,
+            gl::INT_SAMPLER_3D => Self::IntSampler3D,
+            gl::INT_SAMPLER_CUBE => Self::IntSamplerCube,
+            gl::INT_SAMPLER_1D_ARRAY => Self::IntSampler1DArray,
+            gl::INT_SAMPLER_2D_ARRAY => Self::IntSampler2DArray,
+            gl::INT_SAMPLER_CUBE_MAP_ARRAY => Self::IntSamplerCubeMapArray,
+            gl::INT_SAMPLER_2D_MULTISAMPLE => Self::IntSampler2DMultiSample,
+            gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY => Self::IntSampler2DMultiSampleArray,
+            gl::INT_SAMPLER_BUFFER => Self::IntSamplerBuffer,
+            gl::INT_SAMPLER_2D_RECT => Self::IntSampler2DRect,
+
+            gl::UNSIGNED_INT_SAMPLER_1D => Self::UIntSampler1D,
+            gl::UNSIGNED_INT_SAMPLER_2D => Self::UIntSampler2D,
+            gl::UNSIGNED_INT_SAMPLER_3D => Self::UIntSampler3D,
+            gl::UNSIGNED_INT_SAMPLER_CUBE => Self::UIntSamplerCube,
+            gl::UNSIGNED_INT_SAMPLER_1D_ARRAY => Self::UIntSampler1DArray,
+            gl::UNSIGNED_INT_SAMPLER_2D_ARRAY => Self::UIntSampler2DArray,
+            gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY => Self::UIntSamplerCubeMapArray,
+            gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE => Self::UIntSampler2DMultiSample,
+            gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY => Self::UIntSampler2DMultiSampleArray,
+            gl::UNSIGNED_INT_SAMPLER_BUFFER => Self::UIntSamplerBuffer,
+            gl::UNSIGNED_INT_SAMPLER_2D_RECT => Self::UIntSampler2DRect,
+
+            gl::IMAGE_1D => Self::Image1D,
+            gl::IMAGE_2D => Self::Image2D,
+            gl::IMAGE_3D => Self::Image3D,
+            gl::IMAGE_CUBE => Self::ImageCube,
+            gl::IMAGE_1D_ARRAY => Self::Image1DArray,
+            gl::IMAGE_2D_ARRAY => Self::Image2DArray,
+            gl::IMAGE_CUBE_MAP_ARRAY => Self::ImageCubeMapArray,
+            gl::IMAGE_2D_MULTISAMPLE => Self::Image2DMultiSample,
+            gl::IMAGE_2D_MULTISAMPLE_ARRAY => Self::Image2DMultiSampleArray,
+            gl::IMAGE_BUFFER => Self::ImageBuffer,
+            gl::IMAGE_2D_RECT => Self::Image2DRect,
+
+            gl::INT_IMAGE_1D => Self::IntImage1D,
+            gl::INT_IMAGE_2D => Self::IntImage2D,
+            gl::INT_IMAGE_3D => Self::IntImage3D,
+            gl::INT_IMAGE_CUBE => Self::IntImageCube,
+            gl::INT_IMAGE_1D_ARRAY => Self::IntImage1DArray,
+            gl::INT_IMAGE_2D_ARRAY => Self::IntImage2DArray,
+            gl::INT_IMAGE_CUBE_MAP_ARRAY => Self::IntImageCubeMapArray,
+            gl::INT_IMAGE_2D_MULTISAMPLE => Self::IntImage2DMultiSample,
+            gl::INT_IMAGE_2D_MULTISAMPLE_ARRAY => Self::IntImage2DMultiSampleArray,
+            gl::INT_IMAGE_BUFFER => Self::IntImageBuffer,
+            gl::INT_IMAGE_2D_RECT => Self::IntImage2DRect,
+
+            gl::UNSIGNED_INT_IMAGE_1D => Self::UIntImage1D,
+            gl::UNSIGNED_INT_IMAGE_2D => Self::UIntImage2D,
+            gl::UNSIGNED_INT_IMAGE_3D => Self::UIntImage3D,
+            gl::UNSIGNED_INT_IMAGE_CUBE => Self::UIntImageCube,
+            gl::UNSIGNED_INT_IMAGE_1D_ARRAY => Self::UIntImage1DArray,
+            gl::UNSIGNED_INT_IMAGE_2D_ARRAY => Self::UIntImage2DArray,
+            gl::UNSIGNED_INT_IMAGE_CUBE_MAP_ARRAY => Self::UIntImageCubeMapArray,
+            gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE => Self::UIntImage2DMultiSample,
+            gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE_ARRAY => Self::UIntImage2DMultiSampleArray,
+            gl::UNSIGNED_INT_IMAGE_BUFFER => Self::UIntImageBuffer,
+            gl::UNSIGNED_INT_IMAGE_2D_RECT => Self::UIntImage2DRect,
+
+            gl::UNSIGNED_INT_ATOMIC_COUNTER => Self::UIntAtomicCounter,
+
+            x => return Err(UnknownUniformType(x))
+        })
+    }
+}
+
+/// Returned when the driver reports a uniform type this crate doesn't know about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownUniformType(pub u32);
+
+impl Display for UnknownUniformType{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown uniform type 0x{:X}", self.0)
+    }
+}
+
+impl Error for UnknownUniformType{}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UniformError{
+    /// The program has no active uniform with this name.
+    NotFound(String),
+    UnknownType(UnknownUniformType),
+    /// The value's layout doesn't match the reflected uniform type.
+    TypeMismatch{
+        expected: UniformType,
+        found: &'static str
+    },
+    /// The value holds more array elements than the uniform declares.
+    TooManyElements{
+        size: i32,
+        count: usize
+    },
+    /// Uniforms of this type can't be written with glUniform*.
+    NotAssignable(UniformType),
+    /// The uniform is a struct or an array of structs, only its members can be written.
+    Aggregate(String),
+    /// A texture was given to a sampler of a different target, like a cube map to a `sampler2D`.
+    TextureTarget{
+        expected: UniformType,
+        target: u32
+    },
+    /// Every texture unit is in use.
+    OutOfTextureUnits,
+}
+
+impl Display for UniformError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self{
+            UniformError::NotFound(name) => write!(f, "No active uniform named {}", name),
+            UniformError::UnknownType(x) => Display::fmt(x, f),
+            UniformError::TypeMismatch { expected, found } => write!(f, "Can't write {} to a uniform of type {:?}", found, expected),
+            UniformError::TooManyElements { size, count } => write!(f, "Can't write {} elements to a uniform of size {}", count, size),
+            UniformError::NotAssignable(type_) => write!(f, "Uniforms of type {:?} can't be assigned", type_),
+            UniformError::Aggregate(name) => write!(f, "Uniform {} is a struct or an array of structs", name),
+            UniformError::TextureTarget { expected, target } => write!(f, "Can't bind a texture with target 0x{:X} to a uniform of type {:?}", target, expected),
+            UniformError::OutOfTextureUnits => write!(f, "Out of texture units"),
         }
     }
 }
 
+impl Error for UniformError{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self{
+            UniformError::UnknownType(x) => Some(x),
+            _ => None
+        }
+    }
+}
+
+impl From<UnknownUniformType> for UniformError{
+    fn from(x: UnknownUniformType) -> Self {
+        Self::UnknownType(x)
+    }
+}
+
 pub trait UniformContainer{
-    fn bind(&self);
+    /// Writes the values to the uniforms of `program`, textures are bound to units from `units`.
+    fn bind(&self, program: &Program, units: &mut TextureUnits);
 }
 
+#[cfg(feature = "derive")]
+pub use gl_wrapper_derive::UniformContainer;
+
+/// Declares a struct holding the uniforms of a program together with their values.
+///
+/// Binding the container to a program other than the one it was created for, like the stages
+/// of a pipeline, looks the uniforms up in that program instead.
+///
+/// Fields accept the attributes
+/// - `#[name = "u_ModelView"]` to look the uniform up under a different GLSL name,
+/// - `#[optional]` to skip uniforms the compiler optimized away instead of failing,
+/// - `#[unit = 2]` on `i32` sampler fields to initialize them with a texture unit that
+///   isn't handed out to [`TextureBinding`](crate::texture::TextureBinding) fields.
+///
+/// ```ignore
+/// make_container!(struct Uniforms{
+///     #[name = "u_ModelView"]
+///     model_view: Mat4,
+///     #[optional]
+///     tint: Vec4,
+///     lights: [Vec3; 4],
+///     #[unit = 0]
+///     albedo: i32,
+///     normal_map: Option<TextureBinding>,
+/// });
+/// ```
 #[macro_export]
 macro_rules! make_container {
     (struct $name:ident{
         $(
+            $(#[$($attr:tt)*])*
             $fname:ident : $ftype:ty
         ),*
         $(,)?
     }) => {
         pub struct $name{
             $(
-                pub $fname: ($crate::shader::Uniform,$ftype),
+                pub $fname: (Option<$crate::shader::Uniform>,$ftype),
             )*
         }
 
         impl $name{
-            pub fn new(program: &Program) -> Self{
+            /// Panics if a uniform that isn't `#[optional]` is missing.
+            pub fn new(program: &$crate::shader::Program) -> Self{
+                match Self::try_new(program){
+                    Ok(x) => x,
+                    Err(missing) => panic!("Missing uniforms {:?} in {}", missing, stringify!($name))
+                }
+            }
+
+            /// Returns the GLSL names of the missing uniforms that aren't `#[optional]`.
+            pub fn try_new(program: &$crate::shader::Program) -> Result<Self, Vec<&'static str>>{
+                let mut missing = Vec::new();
                 $(
-                    let $fname = program.get_uniform(stringify!($fname)).unwrap();
+                    let $fname = {
+                        let name = $crate::make_container!(@name $fname $(#[$($attr)*])*);
+                        let uniform = program.get_uniform(name);
+                        if uniform.is_none() && !$crate::make_container!(@optional $(#[$($attr)*])*){
+                            missing.push(name);
+                        }
+                        uniform
+                    };
                 )*
-                Self{
+                if !missing.is_empty(){
+                    return Err(missing);
+                }
+                Ok(Self{
                     $(
-                        $fname: ($fname,Default::default()),
+                        $fname: ($fname,$crate::make_container!(@default $(#[$($attr)*])*)),
                     )*
-                }
+                })
             }
         }
 
@@ -423,7 +1387,7 @@ macro_rules! make_container {
             $crate::paste::paste!{
                     impl $name{
                     pub fn [<get_ $fname>](&self) -> $ftype{
-                        self.$fname.1
+                        self.$fname.1.clone()
                     }
     
                     pub fn [<set_ $fname>](&mut self, v: $ftype){
@@ -434,43 +1398,186 @@ macro_rules! make_container {
         )*
         
         impl $crate::shader::UniformContainer for $name{
-            fn bind(&self){
+            fn bind(&self, program: &$crate::shader::Program, units: &mut $crate::texture::TextureUnits){
                 $(
-                    self.$fname.0.set_uniform(&self.$fname.1);
+                    if let Some(unit) = $crate::make_container!(@unit $(#[$($attr)*])*){
+                        units.reserve(unit);
+                    }
+                )*
+                $(
+                    {
+                        let mut write = |uniform: &$crate::shader::Uniform|{
+                            if let Err(e) = $crate::shader::UniformValue::write(&self.$fname.1, uniform, units){
+                                if cfg!(debug_assertions){
+                                    panic!("Failed to set uniform {}: {}", stringify!($fname), e);
+                                }
+                            }
+                        };
+                        // other programs, like the stages of a pipeline, look the uniform up themselves
+                        match &self.$fname.0{
+                            Some(uniform) if uniform.belongs_to(program) => write(uniform),
+                            _ => {
+                                program.with_cached_uniform($crate::make_container!(@name $fname $(#[$($attr)*])*), write);
+                            }
+                        }
+                    }
                 )*
             }
         }
     };
+
+    (@name $fname:ident) => {
+        stringify!($fname)
+    };
+    (@name $fname:ident #[name = $glsl:literal] $($rest:tt)*) => {
+        $glsl
+    };
+    (@name $fname:ident #[optional] $($rest:tt)*) => {
+        $crate::make_container!(@name $fname $($rest)*)
+    };
+    (@name $fname:ident #[unit = $unit:literal] $($rest:tt)*) => {
+        $crate::make_container!(@name $fname $($rest)*)
+    };
+    (@name $fname:ident #[$($attr:tt)*] $($rest:tt)*) => {
+        compile_error!(concat!("Unknown uniform attribute #[", stringify!($($attr)*), "] on ", stringify!($fname)))
+    };
+
+    (@optional) => {
+        false
+    };
+    (@optional #[optional] $($rest:tt)*) => {
+        true
+    };
+    (@optional #[$($attr:tt)*] $($rest:tt)*) => {
+        $crate::make_container!(@optional $($rest)*)
+    };
+
+    (@unit) => {
+        None
+    };
+    (@unit #[unit = $unit:literal] $($rest:tt)*) => {
+        Some($unit)
+    };
+    (@unit #[$($attr:tt)*] $($rest:tt)*) => {
+        $crate::make_container!(@unit $($rest)*)
+    };
+
+    (@default) => {
+        Default::default()
+    };
+    (@default #[unit = $unit:literal] $($rest:tt)*) => {
+        $unit
+    };
+    (@default #[$($attr:tt)*] $($rest:tt)*) => {
+        $crate::make_container!(@default $($rest)*)
+    };
 }
 
 
 
-pub trait UniformValid<T>{
-    fn get_ptr(&self) -> *const T;
+/// A value a [`UniformContainer`] can write to a uniform, either plain data or a
+/// [`TextureBinding`](crate::texture::TextureBinding) that gets a texture unit assigned when drawing.
+pub trait UniformValue{
+    fn write(&self, uniform: &Uniform, units: &mut TextureUnits) -> Result<(), UniformError>;
 }
 
-impl UniformValid<f32> for f32{
+impl<T: UniformValid + ?Sized> UniformValue for T{
+    fn write(&self, uniform: &Uniform, _units: &mut TextureUnits) -> Result<(), UniformError> {
+        uniform.set_uniform(self)
+    }
+}
+
+mod sealed{
+    /// Types that store their components inline, so slices and arrays of them are tightly packed.
+    pub trait Inline{}
+}
+
+/// A value that can be written to a uniform with [`Uniform::set_uniform`].
+///
+/// `get_ptr` must point at `element_count` tightly packed elements, each made of
+/// the `Component`s of one uniform of a type accepted by `matches`.
+pub trait UniformValid{
+    type Component;
+
+    /// Returns true if one element of this value has the layout of `type_`.
+    fn matches(type_: UniformType) -> bool;
+
+    fn get_ptr(&self) -> *const Self::Component;
+
+    /// Number of uniform elements stored in this value, arrays store more than one.
+    fn element_count(&self) -> usize{
+        1
+    }
+}
+
+impl sealed::Inline for f32{}
+
+impl UniformValid for f32{
+    type Component = f32;
+
+    fn matches(type_: UniformType) -> bool {
+        type_ == UniformType::Float
+    }
+
     fn get_ptr(&self) -> *const f32 {
         self
     }
 }
 
-impl UniformValid<i32> for i32{
+impl sealed::Inline for f64{}
+
+impl UniformValid for f64{
+    type Component = f64;
+
+    fn matches(type_: UniformType) -> bool {
+        matches!(type_, UniformType::Double | UniformType::DoubleVec1)
+    }
+
+    fn get_ptr(&self) -> *const f64 {
+        self
+    }
+}
+
+impl sealed::Inline for i32{}
+
+impl UniformValid for i32{
+    type Component = i32;
+
+    fn matches(type_: UniformType) -> bool {
+        matches!(type_, UniformType::Int | UniformType::Bool) || type_.is_sampler() || type_.is_image()
+    }
+
     fn get_ptr(&self) -> *const i32 {
         self
     }
 }
 
-impl UniformValid<u32> for u32{
+impl sealed::Inline for u32{}
+
+impl UniformValid for u32{
+    type Component = u32;
+
+    fn matches(type_: UniformType) -> bool {
+        matches!(type_, UniformType::UInt | UniformType::Bool)
+    }
+
     fn get_ptr(&self) -> *const u32 {
         self
     }
 }
 
 macro_rules! arr_impl {
-    ($prim:ty, $($t:ty)*) => {
+    ($prim:ty, $($t:ty => $($uniform:ident)|*),*) => {
         $(
-            impl UniformValid<$prim> for $t{
+            impl sealed::Inline for $t{}
+
+            impl UniformValid for $t{
+                type Component = $prim;
+
+                fn matches(type_: UniformType) -> bool{
+                    matches!(type_, $(UniformType::$uniform)|*)
+                }
+
                 fn get_ptr(&self) -> *const $prim{
                     self.as_ref().as_ptr()
                 }
@@ -481,31 +1588,144 @@ macro_rules! arr_impl {
 
 arr_impl!(
     f32,
-    Mat4
-    Mat3
-    Vec2
-    Vec3
-    Vec4
-    Quat
-    [f32]
+    Mat4 => FloatMat4,
+    Mat3 => FloatMat3,
+    Mat2 => FloatMat2,
+    Vec2 => FloatVec2,
+    Vec3 => FloatVec3,
+    Vec4 => FloatVec4,
+    Quat => FloatVec4
+);
+
+arr_impl!(
+    f64,
+    DMat4 => DoubleMat4,
+    DMat3 => DoubleMat3,
+    DMat2 => DoubleMat2,
+    DVec2 => DoubleVec2,
+    DVec3 => DoubleVec3,
+    DVec4 => DoubleVec4
 );
 
 arr_impl!(
     i32,
-    IVec2
-    IVec3
-    IVec4
+    IVec2 => IntVec2 | BoolVec2,
+    IVec3 => IntVec3 | BoolVec3,
+    IVec4 => IntVec4 | BoolVec4
 );
 
 arr_impl!(
     u32,
-    UVec2
-    UVec3
-    UVec4
+    UVec2 => UIntVec2 | BoolVec2,
+    UVec3 => UIntVec3 | BoolVec3,
+    UVec4 => UIntVec4 | BoolVec4
 );
 
-impl<T,const X: usize> UniformValid<T> for [T;X]{
-    fn get_ptr(&self) -> *const T {
-        self.as_ptr()
+impl<T: UniformValid + sealed::Inline> UniformValid for [T]{
+    type Component = T::Component;
+
+    fn matches(type_: UniformType) -> bool {
+        T::matches(type_)
+    }
+
+    fn get_ptr(&self) -> *const T::Component {
+        self.as_ptr().cast()
+    }
+
+    fn element_count(&self) -> usize {
+        self.iter().map(T::element_count).sum()
+    }
+}
+
+impl<T: UniformValid + sealed::Inline> UniformValid for Vec<T>{
+    type Component = T::Component;
+
+    fn matches(type_: UniformType) -> bool {
+        T::matches(type_)
+    }
+
+    fn get_ptr(&self) -> *const T::Component {
+        self.as_slice().get_ptr()
+    }
+
+    fn element_count(&self) -> usize {
+        self.as_slice().element_count()
+    }
+}
+
+impl<T: sealed::Inline, const X: usize> sealed::Inline for [T; X]{}
+
+impl<T: UniformValid + sealed::Inline, const X: usize> UniformValid for [T;X]{
+    type Component = T::Component;
+
+    fn matches(type_: UniformType) -> bool {
+        T::matches(type_)
+    }
+
+    fn get_ptr(&self) -> *const T::Component {
+        self.as_ptr().cast()
+    }
+
+    fn element_count(&self) -> usize {
+        self.iter().map(T::element_count).sum()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests{
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn uniform_cache_looks_names_up_once(){
+        let cache = UniformCache::default();
+        let lookups = Cell::new(0);
+        let lookup = |name: &str| {
+            lookups.set(lookups.get() + 1);
+            (name == "color").then_some(7)
+        };
+
+        assert_eq!(cache.get("color", lookup), Some(7));
+        assert_eq!(cache.get("color", lookup), Some(7));
+        assert_eq!(lookups.get(), 1);
+
+        // names the program doesn't have are cached as well, so they are skipped cheaply
+        assert_eq!(cache.get("missing", lookup), None);
+        assert_eq!(cache.get("missing", lookup), None);
+        assert_eq!(lookups.get(), 2);
+    }
+
+    #[test]
+    fn uniform_cache_lookup_can_reenter(){
+        let cache = UniformCache::default();
+        let outer = cache.get("a", |_| cache.get("b", |_| Some(2)).map(|x| x + 1));
+        assert_eq!(outer, Some(3));
+        assert_eq!(cache.get("b", |_| None), Some(2));
+    }
+
+    // binding needs a GL context, this only checks that the generated code compiles
+    #[allow(dead_code)]
+    mod container{
+        use glam::{Mat4, Vec3, Vec4};
+
+        crate::make_container!(struct Container{
+            #[name = "u_ModelView"]
+            model_view: Mat4,
+            #[optional]
+            tint: Vec4,
+            lights: [Vec3; 4],
+            #[unit = 0]
+            albedo: i32,
+        });
+    }
+
+    #[test]
+    fn slices_count_their_elements(){
+        let values = [Vec3::ONE; 4];
+        assert_eq!(values.element_count(), 4);
+        assert_eq!(values[..3].element_count(), 3);
+        assert_eq!(vec![[1.0f32; 2]; 5].element_count(), 10);
+        assert_eq!(values.get_ptr(), values.as_ptr().cast());
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WaitResult{
+    /// The fence was signaled before waiting.
+    AlreadySignaled,
+    /// The fence was signaled while waiting.
+    ConditionSatisfied,
+    TimeoutExpired,
+    /// Waiting failed, usually because the context was lost.
+    Failed,
+}
+
+impl WaitResult{
+    pub fn is_signaled(self) -> bool{
+        matches!(self, Self::AlreadySignaled | Self::ConditionSatisfied)
+    }
+}
+
+/// Signaled once the GPU finished every command issued before it was created.
+pub struct Fence{
+    sync: gl::types::GLsync
+}
+
+impl Fence{
+    pub fn new() -> Self{
+        unsafe{
+            Self{
+                sync: gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0)
+            }
+        }
+    }
+
+    /// Blocks the CPU until the fence is signaled or `timeout` passed, flushing the
+    /// commands so the fence is sure to be reached.
+    pub fn client_wait(&self, timeout: Duration) -> WaitResult{
+        let timeout = timeout.as_nanos().min(u64::MAX as u128) as u64;
+        unsafe{
+            match gl::ClientWaitSync(self.sync, gl::SYNC_FLUSH_COMMANDS_BIT, timeout){
+                gl::ALREADY_SIGNALED => WaitResult::AlreadySignaled,
+                gl::CONDITION_SATISFIED => WaitResult::ConditionSatisfied,
+                gl::TIMEOUT_EXPIRED => WaitResult::TimeoutExpired,
+                _ => WaitResult::Failed
+            }
+        }
+    }
+
+    /// Makes the GPU wait for the fence before running later commands, without blocking the CPU.
+    pub fn wait(&self){
+        unsafe{
+            gl::WaitSync(self.sync, 0, gl::TIMEOUT_IGNORED);
+        }
+    }
+
+    /// Checks the fence without waiting.
+    pub fn is_signaled(&self) -> bool{
+        unsafe{
+            let mut status = 0;
+            gl::GetSynciv(self.sync, gl::SYNC_STATUS, 1, std::ptr::null_mut(), &mut status);
+            status == gl::SIGNALED as _
+        }
+    }
+}
+
+impl Default for Fence{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Fence{
+    fn drop(&mut self) {
+        unsafe{
+            gl::DeleteSync(self.sync);
+        }
+    }
+}
@@ -0,0 +1,806 @@
+use std::{error::Error, fmt::{self, Display, Formatter}, mem::size_of_val, ops::Range, rc::Rc};
+
+use crate::{buffer::Buffer, internal::{RawId, RawIdManager}, pixel::{self, PendingReadback, Pixel, ReadError, Region}, sampler::Sampler, shader::{Uniform, UniformError, UniformValue}};
+
+/// A texture with immutable storage, the size and format are fixed at creation.
+#[derive(Clone)]
+pub struct Texture{
+    target: u32,
+    internal_format: u32,
+    width: i32,
+    height: i32,
+    /// Depth of 3D textures, layers of array textures and 1 otherwise.
+    depth: i32,
+    levels: i32,
+    /// Samples per texel of multisample textures, 0 otherwise.
+    samples: i32,
+    id: Rc<RawId<Self>>
+}
+
+impl Texture{
+    pub fn new_2d(internal_format: u32, width: i32, height: i32, levels: i32) -> Self{
+        let out = Self::create(gl::TEXTURE_2D, internal_format, width, height, 1, levels);
+        unsafe{
+            gl::TextureStorage2D(out.id(), levels, internal_format, width, height);
+        }
+        out
+    }
+
+    /// A cube map, faces are uploaded as layers in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub fn new_cube(internal_format: u32, size: i32, levels: i32) -> Self{
+        let out = Self::create(gl::TEXTURE_CUBE_MAP, internal_format, size, size, 6, levels);
+        unsafe{
+            gl::TextureStorage2D(out.id(), levels, internal_format, size, size);
+        }
+        out
+    }
+
+    pub fn new_2d_array(internal_format: u32, width: i32, height: i32, layers: i32, levels: i32) -> Self{
+        let out = Self::create(gl::TEXTURE_2D_ARRAY, internal_format, width, height, layers, levels);
+        unsafe{
+            gl::TextureStorage3D(out.id(), levels, internal_format, width, height, layers);
+        }
+        out
+    }
+
+    pub fn new_3d(internal_format: u32, width: i32, height: i32, depth: i32, levels: i32) -> Self{
+        let out = Self::create(gl::TEXTURE_3D, internal_format, width, height, depth, levels);
+        unsafe{
+            gl::TextureStorage3D(out.id(), levels, internal_format, width, height, depth);
+        }
+        out
+    }
+
+    /// An array of cube maps, faces are uploaded as layers with `layer * 6 + face`.
+    pub fn new_cube_array(internal_format: u32, size: i32, layers: i32, levels: i32) -> Self{
+        let out = Self::create(gl::TEXTURE_CUBE_MAP_ARRAY, internal_format, size, size, layers * 6, levels);
+        unsafe{
+            gl::TextureStorage3D(out.id(), levels, internal_format, size, size, layers * 6);
+        }
+        out
+    }
+
+    /// A multisampled render target, `fixed_locations` uses the same sample positions for every texel.
+    pub fn new_2d_multisample(internal_format: u32, width: i32, height: i32, samples: i32, fixed_locations: bool) -> Result<Self, UnsupportedSamples>{
+        check_samples(gl::TEXTURE_2D_MULTISAMPLE, internal_format, samples)?;
+        let mut out = Self::create(gl::TEXTURE_2D_MULTISAMPLE, internal_format, width, height, 1, 1);
+        out.samples = samples;
+        unsafe{
+            gl::TextureStorage2DMultisample(out.id(), samples, internal_format, width, height, fixed_locations as _);
+        }
+        Ok(out)
+    }
+
+    pub fn new_2d_multisample_array(internal_format: u32, width: i32, height: i32, layers: i32, samples: i32, fixed_locations: bool) -> Result<Self, UnsupportedSamples>{
+        check_samples(gl::TEXTURE_2D_MULTISAMPLE_ARRAY, internal_format, samples)?;
+        let mut out = Self::create(gl::TEXTURE_2D_MULTISAMPLE_ARRAY, internal_format, width, height, layers, 1);
+        out.samples = samples;
+        unsafe{
+            gl::TextureStorage3DMultisample(out.id(), samples, internal_format, width, height, layers, fixed_locations as _);
+        }
+        Ok(out)
+    }
+
+    fn create(target: u32, internal_format: u32, width: i32, height: i32, depth: i32, levels: i32) -> Self{
+        unsafe{
+            let mut id = 0;
+            gl::CreateTextures(target, 1, &mut id);
+            Self{
+                target,
+                internal_format,
+                width,
+                height,
+                depth,
+                levels,
+                samples: 0,
+                id: Rc::new(RawId::from_id(id))
+            }
+        }
+    }
+
+    /// Creates a texture sharing the storage of `levels` and `layers` of this one, reading
+    /// it as `internal_format`, which has to be in the same compatibility class, like
+    /// `gl::SRGB8_ALPHA8` for `gl::RGBA8`.
+    ///
+    /// A single layer of an array or cube map becomes a 2D (or 1D) view, otherwise the
+    /// view keeps the target. For 3D textures `layers` has to be `0..1`, views of cube maps
+    /// with more than one layer need whole cubes.
+    pub fn create_view(&self, internal_format: u32, levels: Range<u32>, layers: Range<u32>) -> Result<Self, ViewError>{
+        let target = view_target(self.target, self.levels, self.depth, &levels, &layers)?;
+        let layer_count = layers.len() as i32;
+        let depth = if self.target == gl::TEXTURE_3D { (self.depth >> levels.start).max(1) } else { layer_count };
+        unsafe{
+            // views need a name that was never bound, glCreateTextures would give it a target
+            let mut id = 0;
+            gl::GenTextures(1, &mut id);
+            gl::TextureView(id, target, self.id(), internal_format, levels.start, levels.len() as _, layers.start, layers.len() as _);
+            Ok(Self{
+                target,
+                internal_format,
+                width: (self.width >> levels.start).max(1),
+                height: (self.height >> levels.start).max(1),
+                depth,
+                levels: levels.len() as i32,
+                samples: self.samples,
+                id: Rc::new(RawId::from_id(id))
+            })
+        }
+    }
+
+    /// Number of mip levels down to 1x1 for a texture of this size.
+    pub fn mip_levels(width: i32, height: i32) -> i32{
+        32 - (width.max(height).max(1) as u32).leading_zeros() as i32
+    }
+
+    /// Uploads tightly packed pixels to a region of a 2D texture level, panics if `data`
+    /// isn't exactly the size of the region.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_sub_image_2d<T>(&self, level: i32, x: i32, y: i32, width: i32, height: i32, format: u32, type_: u32, data: &[T]){
+        self.set_sub_image_3d(level, x, y, 0, width, height, 1, format, type_, data);
+    }
+
+    /// Uploads tightly packed pixels to a region of a 3D, array or cube map texture level.
+    ///
+    /// For cube maps `z` is the face. Panics if `data` isn't exactly the size of the region
+    /// in `format` and `type_`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_sub_image_3d<T>(&self, level: i32, x: i32, y: i32, z: i32, width: i32, height: i32, depth: i32, format: u32, type_: u32, data: &[T]){
+        let texel_size = bytes_per_texel(format, type_)
+            .unwrap_or_else(|| panic!("Unknown pixel format 0x{:X} and type 0x{:X}", format, type_));
+        let expected = [width, height, depth].into_iter()
+            .try_fold(texel_size, |size, x| size.checked_mul(usize::try_from(x).ok()?))
+            .unwrap_or_else(|| panic!("Invalid region size {}x{}x{}", width, height, depth));
+        if size_of_val(data) != expected{
+            panic!("Pixel data is {} bytes, a {}x{}x{} region needs {}", size_of_val(data), width, height, depth, expected);
+        }
+        unsafe{
+            let mut alignment = 0;
+            gl::GetIntegerv(gl::UNPACK_ALIGNMENT, &mut alignment);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            if self.target == gl::TEXTURE_2D{
+                gl::TextureSubImage2D(self.id(), level, x, y, width, height, format, type_, data.as_ptr().cast());
+            }
+            else{
+                gl::TextureSubImage3D(self.id(), level, x, y, z, width, height, depth, format, type_, data.as_ptr().cast());
+            }
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, alignment);
+        }
+    }
+
+    /// Uploads compressed blocks to a region of a 2D texture level, `format` has to match
+    /// the internal format.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_compressed_sub_image_2d(&self, level: i32, x: i32, y: i32, width: i32, height: i32, format: u32, data: &[u8]){
+        self.set_compressed_sub_image_3d(level, x, y, 0, width, height, 1, format, data);
+    }
+
+    /// Uploads compressed blocks to a region of an array or cube map texture level.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_compressed_sub_image_3d(&self, level: i32, x: i32, y: i32, z: i32, width: i32, height: i32, depth: i32, format: u32, data: &[u8]){
+        unsafe{
+            if self.target == gl::TEXTURE_2D{
+                gl::CompressedTextureSubImage2D(self.id(), level, x, y, width, height, format, data.len() as _, data.as_ptr().cast());
+            }
+            else{
+                gl::CompressedTextureSubImage3D(self.id(), level, x, y, z, width, height, depth, format, data.len() as _, data.as_ptr().cast());
+            }
+        }
+    }
+
+    /// Fills the smaller mip levels from level 0.
+    pub fn generate_mipmaps(&self){
+        unsafe{
+            gl::GenerateTextureMipmap(self.id());
+        }
+    }
+
+    pub fn set_parameter(&self, pname: u32, value: i32){
+        unsafe{
+            gl::TextureParameteri(self.id(), pname, value);
+        }
+    }
+
+    pub fn bind(&self, unit: u32){
+        unsafe{
+            gl::BindTextureUnit(unit, self.id());
+        }
+    }
+
+    /// Binds the texture to `unit` and samples it with `sampler`, or with the texture's
+    /// own parameters for `None`.
+    pub fn bind_with_sampler(&self, unit: u32, sampler: Option<&Sampler>){
+        self.bind(unit);
+        match sampler{
+            Some(sampler) => sampler.bind(unit),
+            None => Sampler::unbind(unit)
+        }
+    }
+
+    /// The whole of mip `level`.
+    pub fn level_region(&self, level: i32) -> Region{
+        let depth = if self.target == gl::TEXTURE_3D { (self.depth >> level).max(1) } else { self.depth };
+        Region{
+            x: 0,
+            y: 0,
+            z: 0,
+            width: (self.width >> level).max(1),
+            height: (self.height >> level).max(1),
+            depth
+        }
+    }
+
+    /// Copies `region` of mip `level` to the CPU, waiting for the GPU to finish writing it.
+    pub fn read_pixels<T: Pixel>(&self, level: i32, region: Region) -> Result<Vec<T>, ReadError>{
+        let size = pixel::read_size::<T>(&region)?;
+        let mut out = vec![T::default(); region.len()];
+        pixel::check_read(|| pixel::with_pack_alignment(|| unsafe{
+            gl::GetTextureSubImage(
+                self.id(), level,
+                region.x, region.y, region.z, region.width, region.height, region.depth,
+                T::FORMAT, T::TYPE, size as _, out.as_mut_ptr().cast()
+            );
+        }))?;
+        Ok(out)
+    }
+
+    /// Starts copying `region` of mip `level` into a pixel pack buffer without waiting.
+    pub fn read_pixels_async<T: Pixel>(&self, level: i32, region: Region) -> Result<PendingReadback<T>, ReadError>{
+        PendingReadback::new(&region, |size| unsafe{
+            gl::GetTextureSubImage(
+                self.id(), level,
+                region.x, region.y, region.z, region.width, region.height, region.depth,
+                T::FORMAT, T::TYPE, size as _, std::ptr::null_mut()
+            );
+        })
+    }
+
+    /// Binds `level` to image `unit` for `imageLoad`/`imageStore`.
+    ///
+    /// With `layered` every layer of an array, cube map or 3D texture is bound, otherwise
+    /// only `layer`. `format` has to have the same texel size as the texture's internal format.
+    pub fn bind_image(&self, unit: u32, level: i32, layered: bool, layer: i32, access: ImageAccess, format: ImageFormat) -> Result<(), ImageBindError>{
+        let texture_size = ImageFormat::from_internal_format(self.internal_format).map(ImageFormat::texel_size);
+        if texture_size != Some(format.texel_size()){
+            return Err(ImageBindError::IncompatibleFormat{
+                internal_format: self.internal_format,
+                format
+            });
+        }
+        if level < 0 || level >= self.levels{
+            return Err(ImageBindError::Level(level));
+        }
+        if !layered && (layer < 0 || layer >= self.depth){
+            return Err(ImageBindError::Layer(layer));
+        }
+        unsafe{
+            gl::BindImageTexture(unit, self.id(), level, layered as _, layer, access as u32, format.internal_format());
+        }
+        Ok(())
+    }
+
+    pub fn target(&self) -> u32{
+        self.target
+    }
+
+    pub fn internal_format(&self) -> u32{
+        self.internal_format
+    }
+
+    pub fn width(&self) -> i32{
+        self.width
+    }
+
+    pub fn height(&self) -> i32{
+        self.height
+    }
+
+    pub fn depth(&self) -> i32{
+        self.depth
+    }
+
+    pub fn levels(&self) -> i32{
+        self.levels
+    }
+
+    /// Samples per texel, 0 if the texture isn't multisampled.
+    pub fn samples(&self) -> i32{
+        self.samples
+    }
+
+    pub(crate) fn id(&self) -> u32{
+        self.id.id()
+    }
+}
+
+impl RawIdManager for Texture{
+    fn create_resource() -> u32 {
+        panic!("Needs to be created manually");
+    }
+
+    fn delete_resource(id: u32) {
+        unsafe{
+            gl::DeleteTextures(1, &id);
+        }
+    }
+}
+
+/// Target of a view of `levels` and `layers` of a texture with `target`, `level_count`
+/// mip levels and `depth` layers.
+fn view_target(target: u32, level_count: i32, depth: i32, levels: &Range<u32>, layers: &Range<u32>) -> Result<u32, ViewError>{
+    if levels.is_empty() || levels.end > level_count as u32{
+        return Err(ViewError::Levels(levels.clone()));
+    }
+    let layer_count = layers.len();
+    let layer_limit = if target == gl::TEXTURE_3D { 1 } else { depth as u32 };
+    if layers.is_empty() || layers.end > layer_limit{
+        return Err(ViewError::Layers(layers.clone()));
+    }
+    Ok(match target{
+        gl::TEXTURE_1D_ARRAY if layer_count == 1 => gl::TEXTURE_1D,
+        gl::TEXTURE_2D_ARRAY | gl::TEXTURE_CUBE_MAP | gl::TEXTURE_CUBE_MAP_ARRAY if layer_count == 1 => gl::TEXTURE_2D,
+        gl::TEXTURE_2D_MULTISAMPLE_ARRAY if layer_count == 1 => gl::TEXTURE_2D_MULTISAMPLE,
+        gl::TEXTURE_CUBE_MAP if layer_count != 6 => return Err(ViewError::Layers(layers.clone())),
+        gl::TEXTURE_CUBE_MAP_ARRAY if !layer_count.is_multiple_of(6) => return Err(ViewError::Layers(layers.clone())),
+        x => x
+    })
+}
+
+/// Mip levels or layers a texture view can't be created from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViewError{
+    /// The range is empty or goes past the last mip level.
+    Levels(Range<u32>),
+    /// The range is empty, goes past the last layer or splits a cube map.
+    Layers(Range<u32>),
+}
+
+impl Display for ViewError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self{
+            ViewError::Levels(x) => write!(f, "Can't create a view of mip levels {:?}", x),
+            ViewError::Layers(x) => write!(f, "Can't create a view of layers {:?}", x),
+        }
+    }
+}
+
+impl Error for ViewError{}
+
+/// A sample count above what the internal format supports, or below 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedSamples{
+    pub requested: i32,
+    pub max: i32,
+}
+
+impl Display for UnsupportedSamples{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} samples requested, between 1 and {} are supported", self.requested, self.max)
+    }
+}
+
+impl Error for UnsupportedSamples{}
+
+/// Size of one tightly packed texel of pixel data in `format` and `type_`.
+fn bytes_per_texel(format: u32, type_: u32) -> Option<usize>{
+    // packed types hold a whole texel
+    let packed = match type_{
+        gl::UNSIGNED_BYTE_3_3_2 | gl::UNSIGNED_BYTE_2_3_3_REV => Some(1),
+        gl::UNSIGNED_SHORT_5_6_5 | gl::UNSIGNED_SHORT_5_6_5_REV
+        | gl::UNSIGNED_SHORT_4_4_4_4 | gl::UNSIGNED_SHORT_4_4_4_4_REV
+        | gl::UNSIGNED_SHORT_5_5_5_1 | gl::UNSIGNED_SHORT_1_5_5_5_REV => Some(2),
+        gl::UNSIGNED_INT_8_8_8_8 | gl::UNSIGNED_INT_8_8_8_8_REV
+        | gl::UNSIGNED_INT_10_10_10_2 | gl::UNSIGNED_INT_2_10_10_10_REV
+        | gl::UNSIGNED_INT_24_8 | gl::UNSIGNED_INT_10F_11F_11F_REV | gl::UNSIGNED_INT_5_9_9_9_REV => Some(4),
+        gl::FLOAT_32_UNSIGNED_INT_24_8_REV => Some(8),
+        _ => None
+    };
+    if packed.is_some(){
+        return packed;
+    }
+
+    let components = match format{
+        gl::RED | gl::GREEN | gl::BLUE | gl::RED_INTEGER | gl::GREEN_INTEGER | gl::BLUE_INTEGER
+        | gl::DEPTH_COMPONENT | gl::STENCIL_INDEX => 1,
+        gl::RG | gl::RG_INTEGER => 2,
+        gl::RGB | gl::BGR | gl::RGB_INTEGER | gl::BGR_INTEGER => 3,
+        gl::RGBA | gl::BGRA | gl::RGBA_INTEGER | gl::BGRA_INTEGER => 4,
+        _ => return None
+    };
+    let component_size = match type_{
+        gl::UNSIGNED_BYTE | gl::BYTE => 1,
+        gl::UNSIGNED_SHORT | gl::SHORT | gl::HALF_FLOAT => 2,
+        gl::UNSIGNED_INT | gl::INT | gl::FLOAT => 4,
+        _ => return None
+    };
+    Some(components * component_size)
+}
+
+/// Checks `samples` against the most `internal_format` supports for `target`, integer and
+/// depth formats usually support fewer than `GL_MAX_SAMPLES`.
+pub(crate) fn check_samples(target: u32, internal_format: u32, samples: i32) -> Result<(), UnsupportedSamples>{
+    let mut max = 0;
+    unsafe{
+        // the counts are listed in descending order, the first is the maximum
+        gl::GetInternalformativ(target, internal_format, gl::SAMPLES, 1, &mut max);
+    }
+    if samples < 1 || samples > max{
+        return Err(UnsupportedSamples{
+            requested: samples,
+            max
+        });
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImageAccess{
+    ReadOnly = gl::READ_ONLY as _,
+    WriteOnly = gl::WRITE_ONLY as _,
+    ReadWrite = gl::READ_WRITE as _,
+}
+
+/// The formats usable with image load/store, matching the GLSL `layout` format qualifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImageFormat{
+    Rgba32f,
+    Rgba16f,
+    Rg32f,
+    Rg16f,
+    R11fG11fB10f,
+    R32f,
+    R16f,
+    Rgba16,
+    Rgb10A2,
+    Rgba8,
+    Rg16,
+    Rg8,
+    R16,
+    R8,
+    Rgba16Snorm,
+    Rgba8Snorm,
+    Rg16Snorm,
+    Rg8Snorm,
+    R16Snorm,
+    R8Snorm,
+    Rgba32i,
+    Rgba16i,
+    Rgba8i,
+    Rg32i,
+    Rg16i,
+    Rg8i,
+    R32i,
+    R16i,
+    R8i,
+    Rgba32ui,
+    Rgba16ui,
+    Rgb10A2ui,
+    Rgba8ui,
+    Rg32ui,
+    Rg16ui,
+    Rg8ui,
+    R32ui,
+    R16ui,
+    R8ui,
+}
+
+impl ImageFormat{
+    /// Every format with its internal format and texel size in bytes.
+    const FORMATS: [(Self, u32, u32); 39] = [
+        (Self::Rgba32f, gl::RGBA32F, 16),
+        (Self::Rgba16f, gl::RGBA16F, 8),
+        (Self::Rg32f, gl::RG32F, 8),
+        (Self::Rg16f, gl::RG16F, 4),
+        (Self::R11fG11fB10f, gl::R11F_G11F_B10F, 4),
+        (Self::R32f, gl::R32F, 4),
+        (Self::R16f, gl::R16F, 2),
+        (Self::Rgba16, gl::RGBA16, 8),
+        (Self::Rgb10A2, gl::RGB10_A2, 4),
+        (Self::Rgba8, gl::RGBA8, 4),
+        (Self::Rg16, gl::RG16, 4),
+        (Self::Rg8, gl::RG8, 2),
+        (Self::R16, gl::R16, 2),
+        (Self::R8, gl::R8, 1),
+        (Self::Rgba16Snorm, gl::RGBA16_SNORM, 8),
+        (Self::Rgba8Snorm, gl::RGBA8_SNORM, 4),
+        (Self::Rg16Snorm, gl::RG16_SNORM, 4),
+        (Self::Rg8Snorm, gl::RG8_SNORM, 2),
+        (Self::R16Snorm, gl::R16_SNORM, 2),
+        (Self::R8Snorm, gl::R8_SNORM, 1),
+        (Self::Rgba32i, gl::RGBA32I, 16),
+        (Self::Rgba16i, gl::RGBA16I, 8),
+        (Self::Rgba8i, gl::RGBA8I, 4),
+        (Self::Rg32i, gl::RG32I, 8),
+        (Self::Rg16i, gl::RG16I, 4),
+        (Self::Rg8i, gl::RG8I, 2),
+        (Self::R32i, gl::R32I, 4),
+        (Self::R16i, gl::R16I, 2),
+        (Self::R8i, gl::R8I, 1),
+        (Self::Rgba32ui, gl::RGBA32UI, 16),
+        (Self::Rgba16ui, gl::RGBA16UI, 8),
+        (Self::Rgb10A2ui, gl::RGB10_A2UI, 4),
+        (Self::Rgba8ui, gl::RGBA8UI, 4),
+        (Self::Rg32ui, gl::RG32UI, 8),
+        (Self::Rg16ui, gl::RG16UI, 4),
+        (Self::Rg8ui, gl::RG8UI, 2),
+        (Self::R32ui, gl::R32UI, 4),
+        (Self::R16ui, gl::R16UI, 2),
+        (Self::R8ui, gl::R8UI, 1),
+    ];
+
+    /// The format for a texture internal format, `None` for formats that can't be used as images.
+    pub fn from_internal_format(internal_format: u32) -> Option<Self>{
+        Self::FORMATS.iter()
+            .find(|(_, x, _)| *x == internal_format)
+            .map(|(format, _, _)| *format)
+    }
+
+    pub fn internal_format(self) -> u32{
+        Self::FORMATS[self as usize].1
+    }
+
+    /// Size of one texel in bytes, formats of the same size can reinterpret each other.
+    pub fn texel_size(self) -> u32{
+        Self::FORMATS[self as usize].2
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageBindError{
+    /// The texture's internal format can't be used as an image or has a different texel size.
+    IncompatibleFormat{
+        internal_format: u32,
+        format: ImageFormat
+    },
+    /// The texture has no such mip level.
+    Level(i32),
+    /// The texture has no such layer.
+    Layer(i32),
+}
+
+impl Display for ImageBindError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self{
+            ImageBindError::IncompatibleFormat { internal_format, format } => write!(f, "Can't access a texture of format 0x{:X} as {:?}", internal_format, format),
+            ImageBindError::Level(x) => write!(f, "Texture has no mip level {}", x),
+            ImageBindError::Layer(x) => write!(f, "Texture has no layer {}", x),
+        }
+    }
+}
+
+impl Error for ImageBindError{}
+
+/// A range of a [`Buffer`] read as a `samplerBuffer` or `imageBuffer`.
+#[derive(Clone)]
+pub struct BufferTexture{
+    texture: Texture,
+    buffer: Buffer,
+    offset: usize,
+    size: usize,
+}
+
+impl BufferTexture{
+    /// Exposes all of `buffer` as texels of `internal_format`.
+    pub fn new(buffer: &Buffer, internal_format: u32) -> Self{
+        let texture = Texture::create(gl::TEXTURE_BUFFER, internal_format, 0, 1, 1, 1);
+        unsafe{
+            gl::TextureBuffer(texture.id(), internal_format, buffer.id());
+            let mut size = 0;
+            gl::GetNamedBufferParameteri64v(buffer.id(), gl::BUFFER_SIZE, &mut size);
+            Self::from_parts(texture, buffer, 0, size as usize)
+        }
+    }
+
+    /// Exposes `size` bytes of `buffer` from `offset`, which has to be a multiple of
+    /// `GL_TEXTURE_BUFFER_OFFSET_ALIGNMENT`.
+    pub fn new_range(buffer: &Buffer, internal_format: u32, offset: usize, size: usize) -> Self{
+        unsafe{
+            let mut alignment = 0;
+            gl::GetIntegerv(gl::TEXTURE_BUFFER_OFFSET_ALIGNMENT, &mut alignment);
+            if !offset.is_multiple_of(alignment.max(1) as usize){
+                panic!("Buffer texture offset {} isn't aligned to {}", offset, alignment);
+            }
+            let texture = Texture::create(gl::TEXTURE_BUFFER, internal_format, 0, 1, 1, 1);
+            gl::TextureBufferRange(texture.id(), internal_format, buffer.id(), offset as isize, size as isize);
+            Self::from_parts(texture, buffer, offset, size)
+        }
+    }
+
+    fn from_parts(mut texture: Texture, buffer: &Buffer, offset: usize, size: usize) -> Self{
+        // buffer textures have no size of their own, report the texel count where it's known
+        if let Some(format) = ImageFormat::from_internal_format(texture.internal_format){
+            texture.width = (size / format.texel_size() as usize) as i32;
+        }
+        Self{
+            texture,
+            buffer: buffer.clone(),
+            offset,
+            size
+        }
+    }
+
+    /// The texture to bind or write to a uniform.
+    pub fn texture(&self) -> &Texture{
+        &self.texture
+    }
+
+    pub fn buffer(&self) -> &Buffer{
+        &self.buffer
+    }
+
+    pub fn offset(&self) -> usize{
+        self.offset
+    }
+
+    pub fn size(&self) -> usize{
+        self.size
+    }
+}
+
+impl UniformValue for BufferTexture{
+    fn write(&self, uniform: &Uniform, units: &mut TextureUnits) -> Result<(), UniformError> {
+        self.texture.write(uniform, units)
+    }
+}
+
+/// A texture and the sampler to read it with, written to a sampler uniform of a
+/// [`UniformContainer`](crate::shader::UniformContainer).
+///
+/// The texture unit is picked when drawing, so containers don't have to track units.
+#[derive(Clone)]
+pub struct TextureBinding{
+    pub texture: Texture,
+    pub sampler: Option<Sampler>,
+}
+
+impl TextureBinding{
+    pub fn new(texture: &Texture) -> Self{
+        Self{
+            texture: texture.clone(),
+            sampler: None
+        }
+    }
+
+    pub fn with_sampler(texture: &Texture, sampler: &Sampler) -> Self{
+        Self{
+            texture: texture.clone(),
+            sampler: Some(sampler.clone())
+        }
+    }
+}
+
+impl From<&Texture> for TextureBinding{
+    fn from(x: &Texture) -> Self {
+        Self::new(x)
+    }
+}
+
+/// Binds `texture` and `sampler` to a free unit and writes it to the sampler uniform.
+fn write_texture(texture: &Texture, sampler: Option<&Sampler>, uniform: &Uniform, units: &mut TextureUnits) -> Result<(), UniformError>{
+    let type_ = uniform.uniform_type().ok_or_else(|| UniformError::Aggregate(uniform.name().to_string()))?;
+    match type_.texture_target(){
+        Some(target) if type_.is_sampler() => {
+            if target != texture.target{
+                return Err(UniformError::TextureTarget{
+                    expected: type_,
+                    target: texture.target
+                });
+            }
+        },
+        _ => return Err(UniformError::TypeMismatch{
+            expected: type_,
+            found: "TextureBinding"
+        })
+    }
+    let unit = units.bind(texture, sampler)?;
+    uniform.set_uniform(&(unit as i32))
+}
+
+impl UniformValue for TextureBinding{
+    fn write(&self, uniform: &Uniform, units: &mut TextureUnits) -> Result<(), UniformError> {
+        write_texture(&self.texture, self.sampler.as_ref(), uniform, units)
+    }
+}
+
+/// Leaves the uniform alone for `None`, for containers that need a `Default` value.
+impl UniformValue for Option<TextureBinding>{
+    fn write(&self, uniform: &Uniform, units: &mut TextureUnits) -> Result<(), UniformError> {
+        match self{
+            Some(binding) => binding.write(uniform, units),
+            None => Ok(())
+        }
+    }
+}
+
+impl UniformValue for Texture{
+    fn write(&self, uniform: &Uniform, units: &mut TextureUnits) -> Result<(), UniformError> {
+        write_texture(self, None, uniform, units)
+    }
+}
+
+impl UniformValue for &Texture{
+    fn write(&self, uniform: &Uniform, units: &mut TextureUnits) -> Result<(), UniformError> {
+        write_texture(self, None, uniform, units)
+    }
+}
+
+/// A texture read with a sampler, like [`TextureBinding::with_sampler`] without the clones.
+impl UniformValue for (&Texture, &Sampler){
+    fn write(&self, uniform: &Uniform, units: &mut TextureUnits) -> Result<(), UniformError> {
+        write_texture(self.0, Some(self.1), uniform, units)
+    }
+}
+
+/// Hands out texture units while the uniforms of a draw are written.
+///
+/// The same texture and sampler pair shares one unit, and units reserved for
+/// manually assigned samplers are skipped.
+#[derive(Default)]
+pub struct TextureUnits{
+    /// `MAX_COMBINED_TEXTURE_IMAGE_UNITS`, queried on the first allocation.
+    max: Option<u32>,
+    reserved: Vec<u32>,
+    /// Texture and sampler ids bound so far and their units.
+    bound: Vec<(u32, u32, u32)>,
+    next: u32,
+}
+
+impl TextureUnits{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    /// Keeps `unit` from being handed out.
+    pub fn reserve(&mut self, unit: u32){
+        self.reserved.push(unit);
+    }
+
+    /// Binds `texture` and `sampler` to a free unit, or returns the unit they're already on.
+    pub fn bind(&mut self, texture: &Texture, sampler: Option<&Sampler>) -> Result<u32, UniformError>{
+        let key = (texture.id(), sampler.map_or(0, Sampler::id));
+        if let Some((_, _, unit)) = self.bound.iter().find(|(t, s, _)| (*t, *s) == key){
+            return Ok(*unit);
+        }
+
+        let max = *self.max.get_or_insert_with(|| unsafe{
+            let mut max = 0;
+            gl::GetIntegerv(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS, &mut max);
+            max as u32
+        });
+        while self.reserved.contains(&self.next){
+            self.next += 1;
+        }
+        if self.next >= max{
+            return Err(UniformError::OutOfTextureUnits);
+        }
+
+        let unit = self.next;
+        self.next += 1;
+        texture.bind_with_sampler(unit, sampler);
+        self.bound.push((key.0, key.1, unit));
+        Ok(unit)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn views_stay_within_the_texture(){
+        assert_eq!(view_target(gl::TEXTURE_2D, 4, 1, &(1..4), &(0..1)), Ok(gl::TEXTURE_2D));
+        assert_eq!(view_target(gl::TEXTURE_2D, 4, 1, &(2..5), &(0..1)), Err(ViewError::Levels(2..5)));
+        assert_eq!(view_target(gl::TEXTURE_2D, 4, 1, &(40..41), &(0..1)), Err(ViewError::Levels(40..41)));
+        assert_eq!(view_target(gl::TEXTURE_2D, 4, 1, &(0..0), &(0..1)), Err(ViewError::Levels(0..0)));
+        assert_eq!(view_target(gl::TEXTURE_2D_ARRAY, 1, 8, &(0..1), &(7..9)), Err(ViewError::Layers(7..9)));
+        assert_eq!(view_target(gl::TEXTURE_3D, 1, 8, &(0..1), &(0..2)), Err(ViewError::Layers(0..2)));
+    }
+
+    #[test]
+    fn single_layers_become_2d_views(){
+        assert_eq!(view_target(gl::TEXTURE_2D_ARRAY, 1, 8, &(0..1), &(3..4)), Ok(gl::TEXTURE_2D));
+        assert_eq!(view_target(gl::TEXTURE_CUBE_MAP, 1, 6, &(0..1), &(5..6)), Ok(gl::TEXTURE_2D));
+        assert_eq!(view_target(gl::TEXTURE_CUBE_MAP, 1, 6, &(0..1), &(0..6)), Ok(gl::TEXTURE_CUBE_MAP));
+        assert_eq!(view_target(gl::TEXTURE_CUBE_MAP, 1, 6, &(0..1), &(0..3)), Err(ViewError::Layers(0..3)));
+        assert_eq!(view_target(gl::TEXTURE_CUBE_MAP_ARRAY, 1, 12, &(0..1), &(6..12)), Ok(gl::TEXTURE_CUBE_MAP_ARRAY));
+    }
+}
@@ -0,0 +1,134 @@
+use std::rc::Rc;
+
+use crate::{buffer::Buffer, internal::{RawId, RawIdManager}};
+
+/// How [`ProgramBuilder::transform_feedback_varyings`](crate::shader::ProgramBuilder::transform_feedback_varyings)
+/// lays out the captured outputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FeedbackBufferMode{
+    /// Every varying is written to the buffer at index 0, one vertex after another.
+    Interleaved = gl::INTERLEAVED_ATTRIBS as _,
+    /// Varying `i` is written to the buffer at index `i`.
+    Separate = gl::SEPARATE_ATTRIBS as _,
+}
+
+/// The buffers vertex shader outputs are captured into, and the number of vertices
+/// captured the last time, for [`VertexArray::draw_transform_feedback`](crate::vao::VertexArray::draw_transform_feedback).
+#[derive(Clone)]
+pub struct TransformFeedback{
+    id: Rc<RawId<Self>>,
+    /// Keeps the bound buffers alive.
+    buffers: Vec<(u32, Buffer)>,
+}
+
+impl TransformFeedback{
+    pub fn new() -> Self{
+        Self{
+            id: Rc::new(RawId::new()),
+            buffers: Vec::new()
+        }
+    }
+
+    /// Captures into the whole of `buffer` at binding `index`.
+    pub fn bind_buffer(&mut self, index: u32, buffer: &Buffer){
+        unsafe{
+            gl::TransformFeedbackBufferBase(self.id(), index, buffer.id());
+        }
+        self.set_buffer(index, buffer);
+    }
+
+    /// Captures into `size` bytes of `buffer` from `offset`, which has to be a multiple of 4.
+    pub fn bind_buffer_range(&mut self, index: u32, buffer: &Buffer, offset: usize, size: usize){
+        unsafe{
+            gl::TransformFeedbackBufferRange(self.id(), index, buffer.id(), offset as isize, size as isize);
+        }
+        self.set_buffer(index, buffer);
+    }
+
+    fn set_buffer(&mut self, index: u32, buffer: &Buffer){
+        self.buffers.retain(|(x, _)| *x != index);
+        self.buffers.push((index, buffer.clone()));
+    }
+
+    /// Starts capturing the primitives drawn until the returned scope is dropped.
+    ///
+    /// `primitive_mode` is `gl::POINTS`, `gl::LINES` or `gl::TRIANGLES` and has to match
+    /// the primitives the draws produce.
+    pub fn begin(&self, primitive_mode: u32) -> FeedbackScope<'_>{
+        unsafe{
+            gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, self.id());
+            gl::BeginTransformFeedback(primitive_mode);
+        }
+        FeedbackScope{
+            feedback: self,
+            paused: false
+        }
+    }
+
+    pub(crate) fn id(&self) -> u32{
+        self.id.id()
+    }
+}
+
+impl Default for TransformFeedback{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RawIdManager for TransformFeedback{
+    fn create_resource() -> u32 {
+        unsafe{
+            let mut i = 0;
+            gl::CreateTransformFeedbacks(1, &mut i);
+            i
+        }
+    }
+
+    fn delete_resource(id: u32) {
+        unsafe{
+            gl::DeleteTransformFeedbacks(1, &id);
+        }
+    }
+}
+
+/// Active capture into a [`TransformFeedback`], ends when dropped.
+pub struct FeedbackScope<'a>{
+    feedback: &'a TransformFeedback,
+    paused: bool,
+}
+
+impl FeedbackScope<'_>{
+    /// Stops capturing until [`FeedbackScope::resume`], draws in between aren't recorded.
+    pub fn pause(&mut self){
+        if !self.paused{
+            unsafe{
+                gl::PauseTransformFeedback();
+            }
+            self.paused = true;
+        }
+    }
+
+    pub fn resume(&mut self){
+        if self.paused{
+            unsafe{
+                gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, self.feedback.id());
+                gl::ResumeTransformFeedback();
+            }
+            self.paused = false;
+        }
+    }
+
+    /// Ends capturing now instead of at the end of the scope.
+    pub fn end(self){}
+}
+
+impl Drop for FeedbackScope<'_>{
+    fn drop(&mut self) {
+        unsafe{
+            gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, self.feedback.id());
+            gl::EndTransformFeedback();
+            gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, 0);
+        }
+    }
+}
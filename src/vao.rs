@@ -1,27 +1,63 @@
 use std::{collections::BTreeMap, rc::Rc, ffi::c_void};
 
-use crate::{buffer::Buffer, internal::{RawId, RawIdManager}, shader::{Program, UniformContainer}};
+use crate::{buffer::Buffer, internal::{RawId, RawIdManager}, pipeline::ShaderProgram, query::{ConditionalMode, Query}, shader::UniformContainer, transform_feedback::TransformFeedback};
 
 #[derive(Clone)]
 pub struct VertexArray{
     pointers: BTreeMap<u32, Buffer>,
     id: Rc<RawId<Self>>,
     indices: Option<Buffer>,
+    index_type: IndexType,
+    restart_index: Option<u32>,
     draw_mode: u32,
     count: i32,
 }
 
+/// The type of the values in an index buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IndexType{
+    U8 = gl::UNSIGNED_BYTE as _,
+    #[default]
+    U16 = gl::UNSIGNED_SHORT as _,
+    U32 = gl::UNSIGNED_INT as _,
+}
+
+impl IndexType{
+    /// Size of one index in bytes.
+    pub fn size(self) -> usize{
+        match self{
+            IndexType::U8 => 1,
+            IndexType::U16 => 2,
+            IndexType::U32 => 4,
+        }
+    }
+}
+
+/// A range of the index buffer drawn with its indices offset by `base_vertex`, for
+/// meshes packed into shared buffers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SubMesh{
+    /// Position of the first index in the index buffer, in indices.
+    pub first_index: i32,
+    pub count: i32,
+    /// Added to every index before fetching vertices.
+    pub base_vertex: i32,
+}
+
 impl VertexArray{
     pub fn new() -> Self{
         Self{
             pointers: BTreeMap::new(),
             id: Rc::new(RawId::new()),
             indices: None,
+            index_type: IndexType::U16,
+            restart_index: None,
             draw_mode: gl::POINTS,
             count: 0
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn set_pointer(
         &mut self,
         pointer: u32,
@@ -49,23 +85,146 @@ impl VertexArray{
         }
     }
 
-    pub fn draw(&self, program: &Program, uniforms: &dyn UniformContainer){
+    pub fn draw(&self, program: &dyn ShaderProgram, uniforms: &dyn UniformContainer){
+        self.draw_part(program, uniforms, self.indices.is_some(), 0, self.count);
+    }
+
+    /// Draws `count` vertices from vertex `first`.
+    ///
+    /// Panics if the vertex array has an index buffer, use [`VertexArray::draw_elements_range`]
+    /// for those, or if the range isn't within the vertex count.
+    pub fn draw_range(&self, program: &dyn ShaderProgram, uniforms: &dyn UniformContainer, first: i32, count: i32){
+        if self.indices.is_some(){
+            panic!("draw_range would ignore the index buffer, use draw_elements_range");
+        }
+        self.check_range(first, count);
+        self.draw_part(program, uniforms, false, first, count);
+    }
+
+    /// Draws `count` indices from index `offset` of the index buffer.
+    ///
+    /// Panics without an index buffer, or if the range isn't within the index count.
+    pub fn draw_elements_range(&self, program: &dyn ShaderProgram, uniforms: &dyn UniformContainer, offset: i32, count: i32){
+        if self.indices.is_none(){
+            panic!("draw_elements_range needs an index buffer");
+        }
+        self.check_range(offset, count);
+        self.draw_part(program, uniforms, true, offset, count);
+    }
+
+    fn check_range(&self, first: i32, count: i32){
+        let in_range = first >= 0 && count >= 0 && first.checked_add(count).is_some_and(|end| end <= self.count);
+        if !in_range{
+            panic!("Can't draw {} elements from {}, the vertex array has {}", count, first, self.count);
+        }
+    }
+
+    fn draw_part(&self, program: &dyn ShaderProgram, uniforms: &dyn UniformContainer, indexed: bool, first: i32, count: i32){
+        self.prepare(program, uniforms);
+        if indexed{
+            self.with_indices(|| unsafe{
+                gl::DrawElements(self.draw_mode, count, self.index_type as u32, self.index_offset(first));
+            });
+        }
+        else{
+            unsafe{
+                gl::DrawArrays(self.draw_mode, first, count);
+            }
+        }
+    }
+
+    /// Draws `count` indices from `first_index` with `base_vertex` added to each.
+    ///
+    /// Panics without an index buffer, or if the range isn't within the index count.
+    pub fn draw_base_vertex(&self, program: &dyn ShaderProgram, uniforms: &dyn UniformContainer, first_index: i32, count: i32, base_vertex: i32){
+        self.draw_sub_meshes(program, uniforms, &[SubMesh{ first_index, count, base_vertex }]);
+    }
+
+    /// Draws every sub mesh with one call, binding the program and uniforms once.
+    ///
+    /// Panics without an index buffer, or if a sub mesh isn't within the index count.
+    pub fn draw_sub_meshes(&self, program: &dyn ShaderProgram, uniforms: &dyn UniformContainer, meshes: &[SubMesh]){
+        if self.indices.is_none(){
+            panic!("Sub mesh draws need an index buffer");
+        }
+        for mesh in meshes{
+            self.check_range(mesh.first_index, mesh.count);
+        }
+        if meshes.is_empty(){
+            return;
+        }
+        self.prepare(program, uniforms);
+
+        let counts: Vec<i32> = meshes.iter().map(|x| x.count).collect();
+        let offsets: Vec<*const c_void> = meshes.iter().map(|x| self.index_offset(x.first_index)).collect();
+        let base_vertices: Vec<i32> = meshes.iter().map(|x| x.base_vertex).collect();
+        self.with_indices(|| unsafe{
+            gl::MultiDrawElementsBaseVertex(self.draw_mode, counts.as_ptr(), self.index_type as u32, offsets.as_ptr(), meshes.len() as _, base_vertices.as_ptr());
+        });
+    }
+
+    fn prepare(&self, program: &dyn ShaderProgram, uniforms: &dyn UniformContainer){
         program.bind();
-        uniforms.bind();
+        program.bind_uniforms(uniforms);
         self.bind();
+    }
+
+    /// Runs an indexed draw with the index buffer bound and primitive restart set up.
+    ///
+    /// The previous primitive restart state is restored afterwards.
+    fn with_indices(&self, draw: impl FnOnce()){
+        let buffer = self.indices.as_ref().unwrap();
+        buffer.bind();
         unsafe{
-            match &self.indices{
-                Some(buffer) => {
-                    buffer.bind();
-                gl::DrawElements(self.draw_mode, self.count, gl::UNSIGNED_SHORT, 0 as *const c_void);
-                    buffer.unbind();
-                },
-                None => {
-                    gl::DrawArrays(self.draw_mode, 0, self.count);
+            match self.restart_index{
+                Some(index) => {
+                    let enabled = gl::IsEnabled(gl::PRIMITIVE_RESTART) == gl::TRUE;
+                    let mut previous = 0;
+                    gl::GetIntegerv(gl::PRIMITIVE_RESTART_INDEX, &mut previous);
+
+                    gl::Enable(gl::PRIMITIVE_RESTART);
+                    gl::PrimitiveRestartIndex(index);
+                    draw();
+
+                    gl::PrimitiveRestartIndex(previous as u32);
+                    if !enabled{
+                        gl::Disable(gl::PRIMITIVE_RESTART);
+                    }
                 },
+                None => draw(),
             }
         }
-        
+        buffer.unbind();
+    }
+
+    /// Byte offset of index `first` in the index buffer, as GL expects it.
+    fn index_offset(&self, first: i32) -> *const c_void{
+        (first as usize * self.index_type.size()) as *const c_void
+    }
+
+    /// Draws only if the samples counted by the occlusion `query` passed.
+    ///
+    /// Panics if `query` isn't an occlusion query.
+    pub fn draw_conditional(&self, program: &dyn ShaderProgram, uniforms: &dyn UniformContainer, query: &Query, mode: ConditionalMode){
+        if !query.query_type().is_occlusion(){
+            panic!("Conditional rendering needs an occlusion query, got {:?}", query.query_type());
+        }
+        unsafe{
+            gl::BeginConditionalRender(query.id(), mode as u32);
+        }
+        self.draw(program, uniforms);
+        unsafe{
+            gl::EndConditionalRender();
+        }
+    }
+
+    /// Draws as many vertices as were last captured into `feedback`, without reading the
+    /// count back to the CPU. The attribute pointers should read the captured buffers.
+    pub fn draw_transform_feedback(&self, program: &dyn ShaderProgram, uniforms: &dyn UniformContainer, feedback: &TransformFeedback){
+        self.prepare(program, uniforms);
+        unsafe{
+            gl::DrawTransformFeedback(self.draw_mode, feedback.id());
+        }
     }
 
 
@@ -86,6 +245,18 @@ impl VertexArray{
         self.count = count;
     }
 
+    /// The type of the indices, [`IndexType::U16`] unless set.
+    pub fn set_index_type(&mut self, type_: IndexType){
+        self.index_type = type_;
+    }
+
+    /// Starts a new strip or fan whenever `index` is read during indexed draws, `None` disables it.
+    ///
+    /// Usually the largest value of the index type, like `u16::MAX as u32`.
+    pub fn set_primitive_restart(&mut self, index: Option<u32>){
+        self.restart_index = index;
+    }
+
     pub fn set_count(&mut self, count: i32){
         self.count = count;
     }
@@ -113,6 +284,12 @@ impl VertexArray{
 
 
 
+impl Default for VertexArray{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RawIdManager for VertexArray{
     fn create_resource() -> u32 {
         unsafe{
@@ -0,0 +1,58 @@
+//! Compile checks for `#[derive(UniformContainer)]`.
+//!
+//! Binding a container needs a GL context, which tests don't have. The lazy per-program
+//! lookup the generated code goes through, `Program::with_cached_uniform`, is tested on
+//! its cache in `src/shader.rs`, including that missing uniforms are looked up once and skipped.
+
+use gl_wrapper::{sampler::Sampler, texture::{Texture, TextureBinding}};
+use gl_wrapper_derive::UniformContainer;
+use glam::{Mat4, Vec3};
+
+fn assert_container<T: gl_wrapper::shader::UniformContainer>(){}
+
+#[derive(UniformContainer)]
+pub struct Visibility{
+    /// Doc comments on fields are ignored.
+    pub model: Mat4,
+    pub(crate) color: Vec3,
+    /// The GLSL name differs from the field.
+    #[uniform(name = "u_Time")]
+    time: f32,
+    #[allow(dead_code)]
+    #[uniform(skip)]
+    label: String,
+}
+
+#[derive(UniformContainer)]
+struct NonCopy{
+    weights: Vec<f32>,
+    offsets: [Vec3; 4],
+    albedo: TextureBinding,
+    normal: Option<TextureBinding>,
+    shadow: Texture,
+}
+
+#[derive(UniformContainer)]
+struct Generic<'a, T>{
+    value: T,
+    texture: &'a Texture,
+    sampled: (&'a Texture, &'a Sampler),
+}
+
+#[derive(UniformContainer)]
+struct Bounded<T: Copy, const N: usize> where T: Default{
+    values: [T; N],
+}
+
+#[derive(UniformContainer)]
+struct Empty{}
+
+#[test]
+fn derives_container(){
+    assert_container::<Visibility>();
+    assert_container::<NonCopy>();
+    assert_container::<Generic<'static, f32>>();
+    assert_container::<Generic<'static, Vec<i32>>>();
+    assert_container::<Bounded<u32, 3>>();
+    assert_container::<Empty>();
+}